@@ -0,0 +1,198 @@
+//! Conversions between on-chain amounts (`U256` base units) and simperby amounts (`Decimal`).
+//!
+//! A `Decimal` holds integers up to 2^96 - 1 (about 7.9e28), far below `U256::MAX`, so every
+//! conversion is checked instead of truncating.
+
+use ethers::types::U256;
+use rust_decimal::Decimal;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AmountError {
+    #[error("amount {0} is negative")]
+    Negative(Decimal),
+    #[error("amount {0} is not a whole number of base units")]
+    Fractional(Decimal),
+    #[error("amount {0} doesn't fit in a decimal")]
+    Overflow(U256),
+    #[error("amount {0:?} is not a number")]
+    Malformed(String),
+    #[error("amount {amount:?} has more than {decimals} decimals")]
+    TooManyDecimals { amount: String, decimals: u8 },
+}
+
+/// Converts an amount in base units to a `Decimal`.
+pub fn u256_to_decimal(value: U256) -> Result<Decimal, AmountError> {
+    // `Decimal::MAX` is the largest mantissa with a zero scale.
+    if value > U256::from(Decimal::MAX.mantissa() as u128) {
+        return Err(AmountError::Overflow(value));
+    }
+    Ok(Decimal::from_i128_with_scale(value.as_u128() as i128, 0))
+}
+
+/// Converts a `Decimal` amount to base units, rejecting negative and fractional amounts.
+pub fn decimal_to_u256(amount: Decimal) -> Result<U256, AmountError> {
+    if amount.is_sign_negative() && !amount.is_zero() {
+        return Err(AmountError::Negative(amount));
+    }
+    if !amount.fract().is_zero() {
+        return Err(AmountError::Fractional(amount));
+    }
+    // A whole number normalizes to a zero scale, leaving the value in the mantissa.
+    let amount = amount.normalize();
+    debug_assert_eq!(amount.scale(), 0);
+    Ok(U256::from(amount.mantissa().unsigned_abs()))
+}
+
+/// Formats an amount in base units as a display amount with `decimals` decimals, grouping the
+/// integer digits by thousands and dropping trailing zeros, such as `1,250.5`.
+pub fn format_units(value: U256, decimals: u8) -> String {
+    let digits = value.to_string();
+    let decimals = decimals as usize;
+    let digits = if digits.len() <= decimals {
+        format!("{}{}", "0".repeat(decimals + 1 - digits.len()), digits)
+    } else {
+        digits
+    };
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        grouped
+    } else {
+        format!("{}.{}", grouped, fraction)
+    }
+}
+
+/// Parses a display amount with `decimals` decimals, such as `1,250.5`, into base units.
+pub fn parse_units(amount: &str, decimals: u8) -> Result<U256, AmountError> {
+    let malformed = || AmountError::Malformed(amount.to_owned());
+    let trimmed = amount.trim();
+    let (integer, fraction) = match trimmed.split_once('.') {
+        Some((integer, fraction)) => (integer, fraction),
+        None => (trimmed, ""),
+    };
+    // Thousands separators are only accepted where `format_units` puts them.
+    if let Some((first, rest)) = integer.split_once(',') {
+        if !(1..=3).contains(&first.len()) || rest.split(',').any(|group| group.len() != 3) {
+            return Err(malformed());
+        }
+    }
+    let integer = integer.replace(',', "");
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(malformed());
+    }
+    if !integer
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(malformed());
+    }
+    if fraction.len() > decimals as usize {
+        return Err(AmountError::TooManyDecimals {
+            amount: amount.to_owned(),
+            decimals,
+        });
+    }
+    let digits = format!(
+        "{}{}{}",
+        integer,
+        fraction,
+        "0".repeat(decimals as usize - fraction.len())
+    );
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(U256::zero());
+    }
+    U256::from_dec_str(digits).map_err(|_| malformed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn round_trip() {
+        for value in [U256::zero(), U256::from(1u64), U256::exp10(18)] {
+            assert_eq!(
+                decimal_to_u256(u256_to_decimal(value).unwrap()).unwrap(),
+                value
+            );
+        }
+        let max = U256::from(Decimal::MAX.mantissa() as u128);
+        assert_eq!(u256_to_decimal(max).unwrap(), Decimal::MAX);
+        assert_eq!(
+            u256_to_decimal(max + 1),
+            Err(AmountError::Overflow(max + 1))
+        );
+        assert_eq!(
+            u256_to_decimal(U256::MAX),
+            Err(AmountError::Overflow(U256::MAX))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_amounts() {
+        let scaled = Decimal::from_str("1000.000").unwrap();
+        assert_eq!(decimal_to_u256(scaled).unwrap(), U256::from(1000u64));
+        let fractional = Decimal::from_str("0.5").unwrap();
+        assert_eq!(
+            decimal_to_u256(fractional),
+            Err(AmountError::Fractional(fractional))
+        );
+        assert_eq!(
+            decimal_to_u256(Decimal::from(-1)),
+            Err(AmountError::Negative(Decimal::from(-1)))
+        );
+    }
+
+    #[test]
+    fn display_amounts() {
+        assert_eq!(format_units(U256::from(1_250_500_000u64), 6), "1,250.5");
+        assert_eq!(format_units(U256::from(1u64), 6), "0.000001");
+        assert_eq!(format_units(U256::zero(), 18), "0");
+        assert_eq!(format_units(U256::from(123_456u64), 0), "123,456");
+        assert_eq!(
+            parse_units("1,250.5", 6).unwrap(),
+            U256::from(1_250_500_000u64)
+        );
+        assert_eq!(parse_units(".5", 1).unwrap(), U256::from(5u64));
+        assert_eq!(parse_units("0", 18).unwrap(), U256::zero());
+        assert_eq!(
+            parse_units("0.0000001", 6),
+            Err(AmountError::TooManyDecimals {
+                amount: "0.0000001".to_owned(),
+                decimals: 6
+            })
+        );
+        assert_eq!(
+            parse_units("-1", 6),
+            Err(AmountError::Malformed("-1".to_owned()))
+        );
+    }
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(
+            parse_units("1,250,000", 0).unwrap(),
+            U256::from(1_250_000u64)
+        );
+        assert_eq!(
+            parse_units("125,000.5", 1).unwrap(),
+            U256::from(1_250_005u64)
+        );
+        for amount in ["1,25.5", "1250,000", ",250", "1,,250", "1,250,", "1,2500"] {
+            assert_eq!(
+                parse_units(amount, 6),
+                Err(AmountError::Malformed(amount.to_owned()))
+            );
+        }
+    }
+}
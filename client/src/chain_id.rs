@@ -0,0 +1,57 @@
+//! Guarding signing against RPC endpoints of another chain.
+
+use crate::{EvmClientError, EvmCompatibleChain};
+use ethers_providers::Middleware;
+use eyre::Error;
+use std::sync::atomic::Ordering;
+
+impl EvmCompatibleChain {
+    /// Returns the chain ID the endpoint must report: the configured or preset one, or else the
+    /// one reported at construction, which the signers were built with.
+    pub fn expected_chain_id(&self) -> u64 {
+        self.chain.get_expected_chain_id().unwrap_or(self.chain_id)
+    }
+
+    /// Returns the chain ID the endpoint reported at the last check.
+    pub fn reported_chain_id(&self) -> u64 {
+        self.reported_chain_id.load(Ordering::SeqCst)
+    }
+
+    /// Returns whether transactions may be signed, which they may not once the endpoint has
+    /// reported a chain ID other than the expected one.
+    pub fn is_signing_enabled(&self) -> bool {
+        self.ensure_chain_id().is_ok()
+    }
+
+    /// Fails if the endpoint reported another chain than the expected one, or than the one the
+    /// signers were built with.
+    pub(crate) fn ensure_chain_id(&self) -> Result<(), EvmClientError> {
+        let expected = self.expected_chain_id();
+        let reported = self.reported_chain_id();
+        if reported != expected || self.chain_id != expected {
+            return Err(EvmClientError::ChainIdMismatch { expected, reported });
+        }
+        Ok(())
+    }
+
+    /// Reads the chain ID from the endpoint again, such as after a reconnection, and fails if it
+    /// isn't the expected one. Signing stays refused until the endpoint reports the expected
+    /// chain ID again.
+    ///
+    /// The [`crate::RpcPool`] also checks every endpoint before it serves its first request, so
+    /// a failover never reaches another chain.
+    pub async fn verify_chain_id(&self) -> Result<u64, Error> {
+        let reported = self
+            .provider
+            .get_chainid()
+            .await
+            .map_err(|err| EvmClientError::Rpc(format!("failed to get chain id: {}", err)))?
+            .as_u64();
+        self.reported_chain_id.store(reported, Ordering::SeqCst);
+        if let Err(err) = self.ensure_chain_id() {
+            log::error!("{}; refusing to sign transactions", err);
+            return Err(err.into());
+        }
+        Ok(reported)
+    }
+}
@@ -0,0 +1,83 @@
+//! The chain name the treasury accepts executions for.
+
+use crate::{EvmClientError, EvmCompatibleChain};
+use eyre::Error;
+use simperby_core::{Timestamp, Transaction};
+use simperby_settlement::execution::{create_execution_transaction, Execution, ExecutionMessage};
+use simperby_settlement::SettlementChain;
+
+impl EvmCompatibleChain {
+    /// Returns the chain name configured for the client, which may differ from the treasury's.
+    pub fn configured_chain_name(&self) -> &str {
+        self.chain.get_chain_name()
+    }
+
+    /// Reads the `chainName` of the treasury, which never changes once deployed, so it is only
+    /// read at construction.
+    ///
+    /// `EVMTreasury.execute` reverts unless `Execution.target_chain` is this name, so a warning
+    /// is logged if the configured name differs.
+    pub(crate) async fn read_treasury_chain_name(&self) -> Result<String, Error> {
+        let name = self
+            .treasury()?
+            .chain_name()
+            .call()
+            .await
+            .map_err(EvmClientError::from_contract_error)?;
+        let name = String::from_utf8(name.to_vec()).map_err(|err| EvmClientError::Decode {
+            what: "treasury chain name",
+            reason: err.to_string(),
+        })?;
+        if name != self.configured_chain_name() {
+            log::warn!(
+                "Treasury chain name {:?} differs from the configured {:?}; executions must use it",
+                name,
+                self.configured_chain_name()
+            );
+        }
+        Ok(name)
+    }
+
+    /// Returns the `chainName` of the treasury, read at construction.
+    pub fn get_treasury_chain_name(&self) -> Result<&str, Error> {
+        self.treasury_chain_name
+            .as_deref()
+            .ok_or_else(|| EvmClientError::TreasuryAddressNotSet.into())
+    }
+
+    /// Fails if the configured chain name isn't the treasury's `chainName`.
+    pub fn check_chain_name(&self) -> Result<(), Error> {
+        let treasury = self.get_treasury_chain_name()?;
+        if treasury != self.configured_chain_name() {
+            return Err(EvmClientError::ChainNameMismatch {
+                configured: self.configured_chain_name().to_owned(),
+                treasury: treasury.to_owned(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Builds an execution of `message` addressed to the treasury's chain name, with the next
+    /// contract sequence.
+    pub async fn build_execution(&self, message: ExecutionMessage) -> Result<Execution, Error> {
+        Ok(Execution {
+            target_chain: self.get_treasury_chain_name()?.to_owned(),
+            contract_sequence: self.get_contract_sequence().await?,
+            message,
+        })
+    }
+
+    /// Builds the simperby transaction of an execution of `message`, as
+    /// [`Self::build_execution`] addresses it.
+    pub async fn create_execution_transaction(
+        &self,
+        message: ExecutionMessage,
+        author: String,
+        timestamp: Timestamp,
+    ) -> Result<Transaction, Error> {
+        let execution = self.build_execution(message).await?;
+        create_execution_transaction(&execution, author, timestamp)
+            .map_err(|_| eyre::eyre!("Failed to create execution transaction"))
+    }
+}
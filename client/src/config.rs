@@ -0,0 +1,370 @@
+//! Building clients in code or from configuration files.
+
+use crate::{
+    ChainConfigs, ChainType, EvmCompatibleAddress, EvmCompatibleChain, FeeStrategy, RelayerSigner,
+    ReplacementPolicy,
+};
+use eyre::Error;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+impl ChainConfigs {
+    /// Creates the configs of a chain reached at `rpc_url`, with every other setting left to
+    /// the chain's default.
+    pub fn new(rpc_url: impl Into<String>) -> Self {
+        ChainConfigs {
+            rpc_url: rpc_url.into(),
+            fallback_rpc_urls: Vec::new(),
+            chain_name: None,
+            expected_chain_id: None,
+            fee_strategy: None,
+            confirmations: None,
+            replacement_policy: None,
+            gas_margin_percent: None,
+            pipelined_execute_gas: None,
+            poll_interval: None,
+            treasury_deploy_block: None,
+            log_block_range: None,
+            rpc_request_timeout: None,
+        }
+    }
+
+    /// Adds an endpoint requests fail over to, tried after the earlier ones while they're healthy.
+    pub fn with_fallback_rpc_url(mut self, rpc_url: impl Into<String>) -> Self {
+        self.fallback_rpc_urls.push(rpc_url.into());
+        self
+    }
+
+    pub fn with_chain_name(mut self, chain_name: impl Into<String>) -> Self {
+        self.chain_name = Some(chain_name.into());
+        self
+    }
+
+    pub fn with_expected_chain_id(mut self, chain_id: u64) -> Self {
+        self.expected_chain_id = Some(chain_id);
+        self
+    }
+
+    pub fn with_fee_strategy(mut self, fee_strategy: FeeStrategy) -> Self {
+        self.fee_strategy = Some(fee_strategy);
+        self
+    }
+
+    pub fn with_confirmations(mut self, confirmations: usize) -> Self {
+        self.confirmations = Some(confirmations);
+        self
+    }
+
+    pub fn with_replacement_policy(mut self, replacement_policy: ReplacementPolicy) -> Self {
+        self.replacement_policy = Some(replacement_policy);
+        self
+    }
+
+    pub fn with_gas_margin_percent(mut self, gas_margin_percent: u64) -> Self {
+        self.gas_margin_percent = Some(gas_margin_percent);
+        self
+    }
+
+    pub fn with_pipelined_execute_gas(mut self, gas: u64) -> Self {
+        self.pipelined_execute_gas = Some(gas);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = Some(poll_interval);
+        self
+    }
+
+    pub fn with_treasury_deploy_block(mut self, block: u64) -> Self {
+        self.treasury_deploy_block = Some(block);
+        self
+    }
+
+    pub fn with_log_block_range(mut self, blocks: u64) -> Self {
+        self.log_block_range = Some(blocks);
+        self
+    }
+
+    pub fn with_rpc_request_timeout(mut self, timeout: Duration) -> Self {
+        self.rpc_request_timeout = Some(timeout);
+        self
+    }
+}
+
+/// Builds an [`EvmCompatibleChain`]; see [`EvmCompatibleChain::builder`].
+#[derive(Debug)]
+pub struct EvmCompatibleChainBuilder {
+    chain: ChainType,
+    treasury_address: Option<EvmCompatibleAddress>,
+    relayer: Option<RelayerSigner>,
+}
+
+impl EvmCompatibleChainBuilder {
+    pub fn treasury_address(mut self, treasury_address: EvmCompatibleAddress) -> Self {
+        self.treasury_address = Some(treasury_address);
+        self
+    }
+
+    /// Sets the relayer key. Without one the client is read-only.
+    pub fn relayer(mut self, relayer: RelayerSigner) -> Self {
+        self.relayer = Some(relayer);
+        self
+    }
+
+    /// Connects to the chain.
+    pub async fn build(self) -> Result<EvmCompatibleChain, Error> {
+        EvmCompatibleChain::new(self.chain, self.treasury_address, self.relayer).await
+    }
+}
+
+impl EvmCompatibleChain {
+    /// Starts building a client of `chain`.
+    pub fn builder(chain: ChainType) -> EvmCompatibleChainBuilder {
+        EvmCompatibleChainBuilder {
+            chain,
+            treasury_address: None,
+            relayer: None,
+        }
+    }
+}
+
+/// The network a configuration file refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainKind {
+    Ethereum,
+    Goerli,
+    Sepolia,
+    Holesky,
+    Polygon,
+    Arbitrum,
+    Optimism,
+    Base,
+    BnbChain,
+    Local,
+    Other,
+}
+
+/// Where the relayer key is read from. Secrets are never written in the configuration itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum SignerSource {
+    /// An environment variable holding a mnemonic phrase or a private key.
+    Env { var: String },
+    /// A file holding a mnemonic phrase or a private key.
+    File { path: PathBuf },
+}
+
+impl SignerSource {
+    pub fn load(&self) -> Result<RelayerSigner, Error> {
+        match self {
+            SignerSource::Env { var } => RelayerSigner::from_env(var),
+            SignerSource::File { path } => RelayerSigner::from_file(path),
+        }
+    }
+}
+
+/// The serializable configuration of an [`EvmCompatibleChain`], such as
+///
+/// ```toml
+/// chain = "other"
+/// rpc_url = "http://localhost:8545"
+/// fallback_rpc_urls = ["http://localhost:8546"]
+/// chain_name = "localhost"
+/// expected_chain_id = 31337
+/// treasury_address = "0x9fE46736679d2D9a65F0992F2272dE9f3c7fa6e0"
+/// confirmations = 1
+/// poll_interval_ms = 1000
+///
+/// [signer]
+/// source = "env"
+/// var = "RELAYER_MNEMONIC"
+///
+/// [fee_strategy]
+/// type = "legacy"
+/// ```
+///
+/// Every field but `chain` and `rpc_url` is optional and falls back to the chain's default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EvmChainConfig {
+    pub chain: ChainKind,
+    pub rpc_url: String,
+    /// The endpoints requests fail over to when `rpc_url` is down or lagging.
+    #[serde(default)]
+    pub fallback_rpc_urls: Vec<String>,
+    #[serde(default)]
+    pub chain_name: Option<String>,
+    /// The chain ID the RPC endpoint must report.
+    #[serde(default)]
+    pub expected_chain_id: Option<u64>,
+    /// The treasury address in hex, with or without `0x`.
+    #[serde(default)]
+    pub treasury_address: Option<String>,
+    /// The relayer key. Without one the client is read-only.
+    #[serde(default)]
+    pub signer: Option<SignerSource>,
+    #[serde(default)]
+    pub fee_strategy: Option<FeeStrategy>,
+    #[serde(default)]
+    pub confirmations: Option<usize>,
+    #[serde(default)]
+    pub gas_margin_percent: Option<u64>,
+    /// The gas limit of an execution sent before the light client update it depends on is mined.
+    #[serde(default)]
+    pub pipelined_execute_gas: Option<u64>,
+    /// How often pending transactions are polled, in milliseconds.
+    #[serde(default)]
+    pub poll_interval_ms: Option<u64>,
+    /// How long a relayer transaction may stay unmined before it is replaced, in seconds.
+    #[serde(default)]
+    pub replacement_timeout_secs: Option<u64>,
+    /// How much the fees of a replacement are raised, in percent.
+    #[serde(default)]
+    pub replacement_fee_bump_percent: Option<u64>,
+    #[serde(default)]
+    pub max_replacements: Option<usize>,
+    /// The block the treasury was deployed at, from which its NFT transfers are scanned.
+    #[serde(default)]
+    pub treasury_deploy_block: Option<u64>,
+    /// The number of blocks covered by one `eth_getLogs` query.
+    #[serde(default)]
+    pub log_block_range: Option<u64>,
+    /// How long an RPC request may take before the next endpoint is tried, in milliseconds.
+    #[serde(default)]
+    pub rpc_request_timeout_ms: Option<u64>,
+}
+
+impl EvmChainConfig {
+    pub fn from_json_str(config: &str) -> Result<Self, Error> {
+        serde_json::from_str(config).map_err(|e| eyre::eyre!("Invalid JSON config: {}", e))
+    }
+
+    pub fn from_toml_str(config: &str) -> Result<Self, Error> {
+        toml::from_str(config).map_err(|e| eyre::eyre!("Invalid TOML config: {}", e))
+    }
+
+    /// Reads a `.json` or `.toml` configuration file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path)
+            .map_err(|e| eyre::eyre!("Failed to read config {}: {}", path.display(), e))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json_str(&config),
+            Some("toml") => Self::from_toml_str(&config),
+            _ => Err(eyre::eyre!(
+                "Unknown config format of {}: expected .json or .toml",
+                path.display()
+            )),
+        }
+    }
+
+    /// Returns the chain this configuration describes.
+    pub fn chain_type(&self) -> ChainType {
+        let mut configs = ChainConfigs::new(self.rpc_url.clone());
+        configs.fallback_rpc_urls = self.fallback_rpc_urls.clone();
+        configs.chain_name = self.chain_name.clone();
+        configs.expected_chain_id = self.expected_chain_id;
+        configs.fee_strategy = self.fee_strategy.clone();
+        configs.confirmations = self.confirmations;
+        configs.gas_margin_percent = self.gas_margin_percent;
+        configs.pipelined_execute_gas = self.pipelined_execute_gas;
+        configs.poll_interval = self.poll_interval_ms.map(Duration::from_millis);
+        configs.treasury_deploy_block = self.treasury_deploy_block;
+        configs.log_block_range = self.log_block_range;
+        configs.rpc_request_timeout = self.rpc_request_timeout_ms.map(Duration::from_millis);
+        let mut chain = match self.chain {
+            ChainKind::Ethereum => ChainType::Ethereum(configs),
+            ChainKind::Goerli => ChainType::Goerli(configs),
+            ChainKind::Sepolia => ChainType::Sepolia(configs),
+            ChainKind::Holesky => ChainType::Holesky(configs),
+            ChainKind::Polygon => ChainType::Polygon(configs),
+            ChainKind::Arbitrum => ChainType::Arbitrum(configs),
+            ChainKind::Optimism => ChainType::Optimism(configs),
+            ChainKind::Base => ChainType::Base(configs),
+            ChainKind::BnbChain => ChainType::BnbChain(configs),
+            ChainKind::Local => ChainType::Local(configs),
+            ChainKind::Other => ChainType::Other(configs),
+        };
+        if self.replacement_timeout_secs.is_some()
+            || self.replacement_fee_bump_percent.is_some()
+            || self.max_replacements.is_some()
+        {
+            // The unset fields keep the chain's default.
+            let default = chain.get_replacement_policy();
+            chain.get_configs_mut().replacement_policy = Some(ReplacementPolicy {
+                timeout: self
+                    .replacement_timeout_secs
+                    .map_or(default.timeout, Duration::from_secs),
+                fee_bump_percent: self
+                    .replacement_fee_bump_percent
+                    .unwrap_or(default.fee_bump_percent),
+                max_replacements: self.max_replacements.unwrap_or(default.max_replacements),
+            });
+        }
+        chain
+    }
+
+    /// Loads the relayer key and connects to the chain.
+    pub async fn connect(&self) -> Result<EvmCompatibleChain, Error> {
+        let mut builder = EvmCompatibleChain::builder(self.chain_type());
+        if let Some(treasury_address) = &self.treasury_address {
+            builder =
+                builder.treasury_address(EvmCompatibleAddress::from_hex_str(treasury_address)?);
+        }
+        if let Some(signer) = &self.signer {
+            builder = builder.relayer(signer.load()?);
+        }
+        builder.build().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U256;
+
+    #[test]
+    fn parse_config() {
+        let toml = r#"
+            chain = "other"
+            rpc_url = "http://localhost:8545"
+            chain_name = "localhost"
+            expected_chain_id = 31337
+            replacement_timeout_secs = 60
+
+            [signer]
+            source = "env"
+            var = "RELAYER_MNEMONIC"
+
+            [fee_strategy]
+            type = "fixed_legacy"
+            gas_price = "0x3b9aca00"
+        "#;
+        let config = EvmChainConfig::from_toml_str(toml).unwrap();
+        assert_eq!(
+            config.signer,
+            Some(SignerSource::Env {
+                var: "RELAYER_MNEMONIC".to_owned()
+            })
+        );
+        assert_eq!(
+            config.fee_strategy,
+            Some(FeeStrategy::FixedLegacy {
+                gas_price: U256::from(1_000_000_000u64)
+            })
+        );
+        let chain = config.chain_type();
+        assert_eq!(chain.get_chain_name(), "localhost");
+        assert_eq!(chain.get_configs().expected_chain_id, Some(31337));
+        assert_eq!(
+            chain.get_replacement_policy().timeout,
+            Duration::from_secs(60)
+        );
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(EvmChainConfig::from_json_str(&json).unwrap(), config);
+        assert!(EvmChainConfig::from_json_str(r#"{"chain": "other"}"#).is_err());
+    }
+}
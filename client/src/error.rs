@@ -0,0 +1,296 @@
+//! Typed errors of the client.
+//!
+//! The `SettlementChain` methods return `eyre::Error`; the errors raised by this crate
+//! can be recovered with `err.downcast_ref::<EvmClientError>()`.
+
+use crate::amount::AmountError;
+use ethers::abi::{decode, ParamType, Token};
+use ethers::contract::ContractError;
+use ethers::types::{Address, Bytes, H256, U256};
+use ethers_providers::Middleware;
+
+/// The selector of `Error(string)`, which `require` and `revert` encode their reason with.
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// A reason the treasury contract reverts with.
+///
+/// Each variant is a `require` or `revert` of `EVMTreasury` or `Verify`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TreasuryRevert {
+    #[error("invalid execution hash")]
+    InvalidExecutionHash,
+    #[error("invalid chain")]
+    InvalidChain,
+    #[error("invalid contract sequence")]
+    InvalidContractSequence,
+    #[error("invalid transaction header")]
+    InvalidTransactionHeader,
+    #[error("insufficient ETH balance")]
+    InsufficientEthBalance,
+    #[error("insufficient ERC-20 balance")]
+    InsufficientErc20Balance,
+    #[error("insufficient ERC-721 balance")]
+    InsufficientErc721Balance,
+    #[error("invalid block height")]
+    InvalidBlockHeight,
+    #[error("invalid previous hash")]
+    InvalidPreviousHash,
+    #[error("invalid block timestamp")]
+    InvalidBlockTimestamp,
+    #[error("invalid block author")]
+    InvalidBlockAuthor,
+    #[error("not enough voting power")]
+    NotEnoughVotingPower,
+    #[error("block height is not covered by the light client")]
+    InvalidCommitmentHeight,
+    #[error("merkle proof verification fail")]
+    MerkleProofVerificationFail,
+    #[error("invalid enum order in merkle proof")]
+    InvalidMerkleProofEntry,
+    #[error("invalid proof length")]
+    InvalidProofLength,
+    #[error("invalid signature length")]
+    InvalidSignatureLength,
+    /// A revert without a reason, such as the signer-to-validator check of
+    /// `verifyFinalizationProof`.
+    #[error("reverted without a reason")]
+    WithoutReason,
+    #[error("reverted: {0}")]
+    Other(String),
+}
+
+impl TreasuryRevert {
+    fn known_reasons() -> [(&'static str, TreasuryRevert); 17] {
+        [
+            (
+                "EVMTreasury::execute: Invalid execution hash",
+                TreasuryRevert::InvalidExecutionHash,
+            ),
+            (
+                "EVMTreasury::execute: Invalid chain",
+                TreasuryRevert::InvalidChain,
+            ),
+            (
+                "EVMTreasury::execute: Invalid contract sequence",
+                TreasuryRevert::InvalidContractSequence,
+            ),
+            (
+                "Invalid transaction header",
+                TreasuryRevert::InvalidTransactionHeader,
+            ),
+            (
+                "EVMTreasury::withdrawETH: Insufficient balance",
+                TreasuryRevert::InsufficientEthBalance,
+            ),
+            (
+                "EVMTreasury::withdrawERC20: Insufficient balance",
+                TreasuryRevert::InsufficientErc20Balance,
+            ),
+            (
+                "EVMTreasury::withdrawERC721: Insufficient balance",
+                TreasuryRevert::InsufficientErc721Balance,
+            ),
+            (
+                "Verify::verifyHeaderToHeader: Invalid block height",
+                TreasuryRevert::InvalidBlockHeight,
+            ),
+            (
+                "Verify::verifyHeaderToHeader: Invalid previous hash",
+                TreasuryRevert::InvalidPreviousHash,
+            ),
+            (
+                "Verify::verifyHeaderToHeader: Invalid block timestamp",
+                TreasuryRevert::InvalidBlockTimestamp,
+            ),
+            (
+                "Verify::verifyHeaderToHeader: Invalid block author",
+                TreasuryRevert::InvalidBlockAuthor,
+            ),
+            (
+                "Verify::verifyFinalizationProof: Not enough voting power",
+                TreasuryRevert::NotEnoughVotingPower,
+            ),
+            (
+                "Verify::verifyTransactionCommitment: Invalid block height",
+                TreasuryRevert::InvalidCommitmentHeight,
+            ),
+            (
+                "Verify::verifyTransactionCommitment: Merkle proof verification fail",
+                TreasuryRevert::MerkleProofVerificationFail,
+            ),
+            (
+                "Invalid enum order in merkle proof",
+                TreasuryRevert::InvalidMerkleProofEntry,
+            ),
+            (
+                "Verify::parseProof: Invalid proof length",
+                TreasuryRevert::InvalidProofLength,
+            ),
+            (
+                "invalid signature length",
+                TreasuryRevert::InvalidSignatureLength,
+            ),
+        ]
+    }
+
+    /// Maps the reason string of a revert to its variant.
+    pub fn from_reason(reason: &str) -> TreasuryRevert {
+        Self::known_reasons()
+            .into_iter()
+            .find(|(known, _)| *known == reason)
+            .map(|(_, revert)| revert)
+            .unwrap_or_else(|| TreasuryRevert::Other(reason.to_owned()))
+    }
+
+    /// Decodes the revert data returned by the node.
+    pub fn from_revert_data(data: &Bytes) -> TreasuryRevert {
+        if data.is_empty() {
+            return TreasuryRevert::WithoutReason;
+        }
+        if data.len() > 4 && data[..4] == ERROR_STRING_SELECTOR {
+            if let Ok(tokens) = decode(&[ParamType::String], &data[4..]) {
+                if let Some(Token::String(reason)) = tokens.into_iter().next() {
+                    return TreasuryRevert::from_reason(&reason);
+                }
+            }
+        }
+        TreasuryRevert::Other(format!("0x{}", hex::encode(data)))
+    }
+
+    /// Finds a known reason in an error message, for nodes which only report the reason as text.
+    pub fn from_message(message: &str) -> Option<TreasuryRevert> {
+        Self::known_reasons()
+            .into_iter()
+            .find(|(known, _)| message.contains(known))
+            .map(|(_, revert)| revert)
+    }
+}
+
+/// Why an execution was refused before being sent, with the live treasury state it was
+/// checked against.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ExecutionRejection {
+    #[error("execution targets chain {execution:?} but the treasury is on {treasury:?}")]
+    ChainMismatch { execution: String, treasury: String },
+    #[error("execution has contract sequence {execution} but the treasury is at {treasury}")]
+    ContractSequenceMismatch { execution: u128, treasury: u128 },
+    /// `token` is the zero address for ETH.
+    #[error("treasury holds {available} of {token:?}, less than {required}")]
+    InsufficientBalance {
+        token: Address,
+        required: U256,
+        available: U256,
+    },
+    /// `owner` is `None` if the token doesn't exist.
+    #[error("token {token_id} of {collection:?} is owned by {owner:?}, not the treasury")]
+    NotTokenOwner {
+        collection: Address,
+        token_id: U256,
+        owner: Option<Address>,
+    },
+    #[error("execution message is not supported by the treasury")]
+    UnsupportedMessage,
+}
+
+impl ExecutionRejection {
+    /// Returns the reason the treasury would revert the execution with.
+    pub fn revert(&self) -> TreasuryRevert {
+        match self {
+            ExecutionRejection::ChainMismatch { .. } => TreasuryRevert::InvalidChain,
+            ExecutionRejection::ContractSequenceMismatch { .. } => {
+                TreasuryRevert::InvalidContractSequence
+            }
+            ExecutionRejection::InsufficientBalance { token, .. } if token.is_zero() => {
+                TreasuryRevert::InsufficientEthBalance
+            }
+            ExecutionRejection::InsufficientBalance { .. } => {
+                TreasuryRevert::InsufficientErc20Balance
+            }
+            ExecutionRejection::NotTokenOwner { .. } => TreasuryRevert::InsufficientErc721Balance,
+            ExecutionRejection::UnsupportedMessage => TreasuryRevert::InvalidTransactionHeader,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EvmClientError {
+    #[error("treasury address is not set")]
+    TreasuryAddressNotSet,
+    #[error("relayer signer is not set")]
+    RelayerNotSet,
+    /// The NFT transfers of the treasury on a public network would be scanned from genesis.
+    #[error("treasury deploy block is not set")]
+    TreasuryDeployBlockNotSet,
+    /// The treasury rejected the call, either in the local pre-flight check,
+    /// at simulation or on-chain.
+    #[error("treasury reverted: {0}")]
+    Reverted(#[from] TreasuryRevert),
+    /// The execution was checked against the treasury state and would revert.
+    #[error("execution rejected: {0}")]
+    ExecutionRejected(#[from] ExecutionRejection),
+    /// The transaction reverted on-chain but its reason couldn't be recovered.
+    #[error("transaction {0:?} reverted")]
+    TransactionReverted(H256),
+    /// The RPC endpoint serves another chain than the expected one, so nothing is signed.
+    #[error("RPC endpoint reports chain id {reported}, expected {expected}")]
+    ChainIdMismatch { expected: u64, reported: u64 },
+    /// The configured chain name isn't the `chainName` of the treasury.
+    #[error("configured chain name {configured:?} differs from the treasury's {treasury:?}")]
+    ChainNameMismatch {
+        configured: String,
+        treasury: String,
+    },
+    /// Another transaction was mined with the nonce of a pending relayer transaction, which will
+    /// therefore never be.
+    #[error("nonce {nonce} of transaction {transaction_hash:?} was used by another transaction")]
+    NonceConsumed { nonce: U256, transaction_hash: H256 },
+    #[error("RPC error: {0}")]
+    Rpc(String),
+    /// Data returned by the node or stored on-chain couldn't be decoded.
+    #[error("failed to decode {what}: {reason}")]
+    Decode { what: &'static str, reason: String },
+    /// The transaction needs more gas than a block can hold.
+    #[error(
+        "transaction needs {required} gas, more than the block gas limit of {block_gas_limit}"
+    )]
+    GasLimitExceeded {
+        required: U256,
+        block_gas_limit: U256,
+    },
+    #[error("invalid amount: {0}")]
+    InvalidAmount(#[from] AmountError),
+}
+
+/// Returns whether the contract rejected a call, by reverting or by returning data that doesn't
+/// decode as the expected output, rather than the node failing to answer.
+pub(crate) fn is_contract_rejection<M: Middleware>(err: &ContractError<M>) -> bool {
+    matches!(
+        err,
+        ContractError::Revert(_)
+            | ContractError::DecodingError(_)
+            | ContractError::AbiError(_)
+            | ContractError::DetokenizationError(_)
+    )
+}
+
+impl EvmClientError {
+    /// Converts a contract call error, decoding the treasury revert reason if there is one.
+    pub fn from_contract_error<M: Middleware>(err: ContractError<M>) -> EvmClientError {
+        if let ContractError::Revert(data) = &err {
+            return TreasuryRevert::from_revert_data(data).into();
+        }
+        let message = err.to_string();
+        match TreasuryRevert::from_message(&message) {
+            Some(revert) => revert.into(),
+            None => EvmClientError::Rpc(message),
+        }
+    }
+
+    /// Returns the treasury revert reason, if this error is one.
+    pub fn as_revert(&self) -> Option<&TreasuryRevert> {
+        match self {
+            EvmClientError::Reverted(revert) => Some(revert),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use crate::{EvmClientError, EvmProvider};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{
+    Address, BlockNumber, Eip1559TransactionRequest, FeeHistory, TransactionRequest, U256,
+};
+use ethers_providers::Middleware;
+use eyre::Error;
+use serde::{Deserialize, Serialize};
+
+/// The EIP-1559 headroom: the max fee covers the base fee doubling before inclusion.
+const BASE_FEE_MULTIPLIER: u64 = 2;
+/// The least fee raise nodes accept to replace a pending transaction, in percent.
+const MIN_REPLACEMENT_BUMP_PERCENT: u64 = 10;
+
+/// How the fees of treasury and transfer transactions are priced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeeStrategy {
+    /// Legacy transactions priced at `eth_gasPrice`, optionally capped.
+    Legacy { gas_price_cap: Option<U256> },
+    /// EIP-1559 transactions whose priority fee is taken from `eth_feeHistory`.
+    Eip1559 {
+        /// The number of recent blocks to sample.
+        fee_history_blocks: u64,
+        /// The reward percentile sampled from each block, from 0 to 100.
+        reward_percentile: f64,
+        /// The upper bound of `maxFeePerGas`.
+        max_fee_cap: Option<U256>,
+        /// The upper bound of `maxPriorityFeePerGas`.
+        priority_fee_cap: Option<U256>,
+    },
+    /// Legacy transactions with a fixed gas price.
+    FixedLegacy { gas_price: U256 },
+    /// EIP-1559 transactions with fixed fees.
+    FixedEip1559 {
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    },
+}
+
+impl FeeStrategy {
+    /// The default EIP-1559 strategy: the median priority fee of the last 10 blocks.
+    pub fn eip1559() -> Self {
+        FeeStrategy::Eip1559 {
+            fee_history_blocks: 10,
+            reward_percentile: 50.0,
+            max_fee_cap: None,
+            priority_fee_cap: None,
+        }
+    }
+
+    /// The default legacy strategy: `eth_gasPrice` without a cap.
+    pub fn legacy() -> Self {
+        FeeStrategy::Legacy {
+            gas_price_cap: None,
+        }
+    }
+
+    /// Computes the fees of a transaction to be sent now.
+    pub async fn fees(&self, provider: &EvmProvider) -> Result<TransactionFees, Error> {
+        match self {
+            FeeStrategy::Legacy { gas_price_cap } => {
+                let gas_price = provider.get_gas_price().await.map_err(|err| {
+                    EvmClientError::Rpc(format!("failed to get gas price: {}", err))
+                })?;
+                Ok(TransactionFees::Legacy {
+                    gas_price: cap(gas_price, *gas_price_cap),
+                })
+            }
+            FeeStrategy::Eip1559 {
+                fee_history_blocks,
+                reward_percentile,
+                max_fee_cap,
+                priority_fee_cap,
+            } => {
+                let history = provider
+                    .fee_history(
+                        *fee_history_blocks,
+                        BlockNumber::Latest,
+                        &[*reward_percentile],
+                    )
+                    .await
+                    .map_err(|err| {
+                        EvmClientError::Rpc(format!("failed to get fee history: {}", err))
+                    })?;
+                Ok(eip1559_fees(&history, *max_fee_cap, *priority_fee_cap)?)
+            }
+            FeeStrategy::FixedLegacy { gas_price } => Ok(TransactionFees::Legacy {
+                gas_price: *gas_price,
+            }),
+            FeeStrategy::FixedEip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => Ok(TransactionFees::Eip1559 {
+                max_fee_per_gas: *max_fee_per_gas,
+                max_priority_fee_per_gas: (*max_priority_fee_per_gas).min(*max_fee_per_gas),
+            }),
+        }
+    }
+}
+
+impl FeeStrategy {
+    /// Lowers the fees to the caps of the strategy, if any.
+    pub fn clamp(&self, fees: TransactionFees) -> TransactionFees {
+        match (self, fees) {
+            (FeeStrategy::Legacy { gas_price_cap }, TransactionFees::Legacy { gas_price }) => {
+                TransactionFees::Legacy {
+                    gas_price: cap(gas_price, *gas_price_cap),
+                }
+            }
+            (
+                FeeStrategy::Eip1559 {
+                    max_fee_cap,
+                    priority_fee_cap,
+                    ..
+                },
+                TransactionFees::Eip1559 {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                },
+            ) => {
+                let max_fee = cap(max_fee_per_gas, *max_fee_cap);
+                TransactionFees::Eip1559 {
+                    max_fee_per_gas: max_fee,
+                    max_priority_fee_per_gas: cap(max_priority_fee_per_gas, *priority_fee_cap)
+                        .min(max_fee),
+                }
+            }
+            _ => fees,
+        }
+    }
+
+    /// Computes the fees replacing a pending transaction sent with `previous`: raised by
+    /// `bump_percent`, at least the `current` ones, within the caps of the strategy.
+    ///
+    /// Returns `None` if the caps leave no room for a raise the nodes would accept.
+    pub fn replacement_fees(
+        &self,
+        previous: &TransactionFees,
+        current: &TransactionFees,
+        bump_percent: u64,
+    ) -> Option<TransactionFees> {
+        let fees = self.clamp(previous.bumped(bump_percent).at_least(current));
+        let required = previous.bumped(MIN_REPLACEMENT_BUMP_PERCENT);
+        (fees.at_least(&required) == fees).then_some(fees)
+    }
+}
+
+/// The fees chosen for a single transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionFees {
+    Legacy {
+        gas_price: U256,
+    },
+    Eip1559 {
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    },
+}
+
+impl TransactionFees {
+    /// Sets the fees on the transaction, converting it to a legacy one if required.
+    pub fn apply(&self, tx: &mut TypedTransaction) {
+        match self {
+            TransactionFees::Legacy { gas_price } => {
+                let legacy = match tx {
+                    TypedTransaction::Eip1559(inner) => {
+                        Some(TypedTransaction::Legacy(inner.clone().into()))
+                    }
+                    _ => None,
+                };
+                if let Some(legacy) = legacy {
+                    *tx = legacy;
+                }
+                tx.set_gas_price(*gas_price);
+            }
+            TransactionFees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => match tx {
+                TypedTransaction::Eip1559(inner) => {
+                    inner.max_fee_per_gas = Some(*max_fee_per_gas);
+                    inner.max_priority_fee_per_gas = Some(*max_priority_fee_per_gas);
+                }
+                // A non EIP-1559 transaction can only pay the max fee as its gas price.
+                _ => {
+                    tx.set_gas_price(*max_fee_per_gas);
+                }
+            },
+        }
+    }
+}
+
+impl TransactionFees {
+    /// Builds a plain transfer of `value` to `to`, of the transaction type these fees price.
+    pub fn value_transfer(&self, to: Address, value: U256) -> TypedTransaction {
+        let mut tx: TypedTransaction = match self {
+            TransactionFees::Legacy { .. } => TransactionRequest::new().to(to).value(value).into(),
+            TransactionFees::Eip1559 { .. } => {
+                Eip1559TransactionRequest::new().to(to).value(value).into()
+            }
+        };
+        self.apply(&mut tx);
+        tx
+    }
+
+    /// Raises every fee by `percent`, rounding up.
+    pub fn bumped(&self, percent: u64) -> TransactionFees {
+        let bump = |fee: U256| (fee * (100 + percent) + 99) / 100;
+        match self {
+            TransactionFees::Legacy { gas_price } => TransactionFees::Legacy {
+                gas_price: bump(*gas_price),
+            },
+            TransactionFees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => TransactionFees::Eip1559 {
+                max_fee_per_gas: bump(*max_fee_per_gas),
+                max_priority_fee_per_gas: bump(*max_priority_fee_per_gas),
+            },
+        }
+    }
+
+    /// Takes the larger of each fee, keeping the kind of `self`.
+    pub fn at_least(&self, other: &TransactionFees) -> TransactionFees {
+        match (self, other) {
+            (
+                TransactionFees::Legacy { gas_price },
+                TransactionFees::Legacy {
+                    gas_price: other_gas_price,
+                },
+            ) => TransactionFees::Legacy {
+                gas_price: (*gas_price).max(*other_gas_price),
+            },
+            (
+                TransactionFees::Eip1559 {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                },
+                TransactionFees::Eip1559 {
+                    max_fee_per_gas: other_max_fee_per_gas,
+                    max_priority_fee_per_gas: other_max_priority_fee_per_gas,
+                },
+            ) => TransactionFees::Eip1559 {
+                max_fee_per_gas: (*max_fee_per_gas).max(*other_max_fee_per_gas),
+                max_priority_fee_per_gas: (*max_priority_fee_per_gas)
+                    .max(*other_max_priority_fee_per_gas),
+            },
+            _ => *self,
+        }
+    }
+}
+
+/// Prices an EIP-1559 transaction from the fee history sampled at a single reward percentile:
+/// the median reward, on top of twice the next block's base fee.
+fn eip1559_fees(
+    history: &FeeHistory,
+    max_fee_cap: Option<U256>,
+    priority_fee_cap: Option<U256>,
+) -> Result<TransactionFees, EvmClientError> {
+    // The last entry is the base fee of the next block.
+    let base_fee = history
+        .base_fee_per_gas
+        .last()
+        .copied()
+        .ok_or(EvmClientError::Decode {
+            what: "fee history",
+            reason: "missing base fee".to_owned(),
+        })?;
+    let mut rewards = history
+        .reward
+        .iter()
+        .filter_map(|block| block.first().copied())
+        .collect::<Vec<_>>();
+    rewards.sort();
+    let priority_fee = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+    let priority_fee = cap(priority_fee, priority_fee_cap);
+    let max_fee = cap(base_fee * BASE_FEE_MULTIPLIER + priority_fee, max_fee_cap);
+    Ok(TransactionFees::Eip1559 {
+        max_fee_per_gas: max_fee,
+        max_priority_fee_per_gas: priority_fee.min(max_fee),
+    })
+}
+
+fn cap(value: U256, cap: Option<U256>) -> U256 {
+    cap.map_or(value, |cap| value.min(cap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee_history(base_fee: u64, rewards: &[u64]) -> FeeHistory {
+        FeeHistory {
+            base_fee_per_gas: vec![U256::from(1u64), U256::from(base_fee)],
+            gas_used_ratio: vec![0.5; rewards.len()],
+            oldest_block: U256::zero(),
+            reward: rewards
+                .iter()
+                .map(|reward| vec![U256::from(*reward)])
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn eip1559_fees_from_history() {
+        let history = fee_history(100, &[7, 2, 5, 9, 1]);
+        assert_eq!(
+            eip1559_fees(&history, None, None).unwrap(),
+            TransactionFees::Eip1559 {
+                max_fee_per_gas: U256::from(205u64),
+                max_priority_fee_per_gas: U256::from(5u64),
+            }
+        );
+        assert_eq!(
+            eip1559_fees(&history, Some(U256::from(150u64)), Some(U256::from(3u64))).unwrap(),
+            TransactionFees::Eip1559 {
+                max_fee_per_gas: U256::from(150u64),
+                max_priority_fee_per_gas: U256::from(3u64),
+            }
+        );
+        // The priority fee never exceeds the capped max fee.
+        let history = fee_history(10, &[50]);
+        assert_eq!(
+            eip1559_fees(&history, Some(U256::from(30u64)), None).unwrap(),
+            TransactionFees::Eip1559 {
+                max_fee_per_gas: U256::from(30u64),
+                max_priority_fee_per_gas: U256::from(30u64),
+            }
+        );
+        let empty = FeeHistory {
+            base_fee_per_gas: Vec::new(),
+            gas_used_ratio: Vec::new(),
+            oldest_block: U256::zero(),
+            reward: Vec::new(),
+        };
+        assert!(eip1559_fees(&empty, None, None).is_err());
+    }
+
+    #[test]
+    fn replacement_fees() {
+        let strategy = FeeStrategy::Eip1559 {
+            fee_history_blocks: 10,
+            reward_percentile: 50.0,
+            max_fee_cap: Some(U256::from(130u64)),
+            priority_fee_cap: Some(U256::from(10u64)),
+        };
+        let previous = TransactionFees::Eip1559 {
+            max_fee_per_gas: U256::from(100u64),
+            max_priority_fee_per_gas: U256::from(5u64),
+        };
+        let current = TransactionFees::Eip1559 {
+            max_fee_per_gas: U256::from(90u64),
+            max_priority_fee_per_gas: U256::from(7u64),
+        };
+        assert_eq!(
+            strategy.replacement_fees(&previous, &current, 20),
+            Some(TransactionFees::Eip1559 {
+                max_fee_per_gas: U256::from(120u64),
+                max_priority_fee_per_gas: U256::from(7u64),
+            })
+        );
+        // The caps hold, even if the raise is then smaller than asked.
+        assert_eq!(
+            strategy.replacement_fees(&previous, &current, 50),
+            Some(TransactionFees::Eip1559 {
+                max_fee_per_gas: U256::from(130u64),
+                max_priority_fee_per_gas: U256::from(8u64),
+            })
+        );
+        // No room left under the caps for the least accepted raise.
+        let capped = TransactionFees::Eip1559 {
+            max_fee_per_gas: U256::from(125u64),
+            max_priority_fee_per_gas: U256::from(10u64),
+        };
+        assert_eq!(strategy.replacement_fees(&capped, &current, 20), None);
+
+        let legacy = FeeStrategy::Legacy {
+            gas_price_cap: Some(U256::from(105u64)),
+        };
+        let previous = TransactionFees::Legacy {
+            gas_price: U256::from(100u64),
+        };
+        assert_eq!(legacy.replacement_fees(&previous, &previous, 20), None);
+        assert_eq!(
+            FeeStrategy::legacy().replacement_fees(&previous, &previous, 20),
+            Some(TransactionFees::Legacy {
+                gas_price: U256::from(120u64),
+            })
+        );
+    }
+
+    #[test]
+    fn apply_fees() {
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new().nonce(3).into();
+        TransactionFees::Legacy {
+            gas_price: U256::from(40u64),
+        }
+        .apply(&mut tx);
+        assert!(matches!(tx, TypedTransaction::Legacy(_)));
+        assert_eq!(tx.gas_price(), Some(U256::from(40u64)));
+        assert_eq!(tx.nonce(), Some(&U256::from(3u64)));
+
+        let eip1559 = TransactionFees::Eip1559 {
+            max_fee_per_gas: U256::from(60u64),
+            max_priority_fee_per_gas: U256::from(2u64),
+        };
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new().into();
+        eip1559.apply(&mut tx);
+        match &tx {
+            TypedTransaction::Eip1559(inner) => {
+                assert_eq!(inner.max_fee_per_gas, Some(U256::from(60u64)));
+                assert_eq!(inner.max_priority_fee_per_gas, Some(U256::from(2u64)));
+            }
+            _ => panic!("expected an EIP-1559 transaction"),
+        }
+        let mut tx: TypedTransaction = TransactionRequest::new().into();
+        eip1559.apply(&mut tx);
+        assert_eq!(tx.gas_price(), Some(U256::from(60u64)));
+
+        let transfer = eip1559.value_transfer(Address::zero(), U256::from(7u64));
+        assert!(matches!(transfer, TypedTransaction::Eip1559(_)));
+        assert_eq!(transfer.value(), Some(&U256::from(7u64)));
+    }
+
+    #[test]
+    fn bumped_fees() {
+        let fees = TransactionFees::Eip1559 {
+            max_fee_per_gas: U256::from(100u64),
+            max_priority_fee_per_gas: U256::from(3u64),
+        };
+        assert_eq!(
+            fees.bumped(10),
+            TransactionFees::Eip1559 {
+                max_fee_per_gas: U256::from(110u64),
+                max_priority_fee_per_gas: U256::from(4u64),
+            }
+        );
+        let current = TransactionFees::Eip1559 {
+            max_fee_per_gas: U256::from(200u64),
+            max_priority_fee_per_gas: U256::from(1u64),
+        };
+        assert_eq!(
+            fees.bumped(10).at_least(&current),
+            TransactionFees::Eip1559 {
+                max_fee_per_gas: U256::from(200u64),
+                max_priority_fee_per_gas: U256::from(4u64),
+            }
+        );
+    }
+}
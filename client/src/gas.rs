@@ -0,0 +1,137 @@
+//! Gas limits of relayer transactions.
+
+use crate::{EvmClientError, EvmCompatibleChain, RelayerClient};
+use ethers::contract::ContractCall;
+use ethers::types::{BlockNumber, U256};
+use ethers_providers::Middleware;
+use eyre::Error;
+
+/// The default headroom added to estimated gas, in percent.
+pub const DEFAULT_GAS_MARGIN_PERCENT: u64 = 20;
+/// The default gas limit of an execution sent before the light client update it depends on is
+/// mined, which can't be estimated as it would revert against the current state.
+pub const DEFAULT_PIPELINED_EXECUTE_GAS: u64 = 1_000_000;
+
+impl EvmCompatibleChain {
+    /// Returns the gas limit of the latest block.
+    pub async fn block_gas_limit(&self) -> Result<U256, Error> {
+        let block = self
+            .provider
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|err| EvmClientError::Rpc(format!("failed to get latest block: {}", err)))?
+            .ok_or_else(|| EvmClientError::Rpc("latest block not found".to_owned()))?;
+        Ok(block.gas_limit)
+    }
+
+    /// Fails if `gas` doesn't fit in a block.
+    pub(crate) async fn check_block_gas_limit(&self, gas: U256) -> Result<U256, Error> {
+        let block_gas_limit = self.block_gas_limit().await?;
+        if gas > block_gas_limit {
+            return Err(EvmClientError::GasLimitExceeded {
+                required: gas,
+                block_gas_limit,
+            }
+            .into());
+        }
+        Ok(block_gas_limit)
+    }
+
+    /// Estimates the gas of `call` and adds `margin_percent` of headroom, without going over the
+    /// block gas limit.
+    ///
+    /// Fails if the estimate alone doesn't fit in a block, as the transaction could never be mined.
+    pub(crate) async fn estimate_gas_with_margin(
+        &self,
+        call: &ContractCall<RelayerClient, ()>,
+        margin_percent: u64,
+    ) -> Result<U256, Error> {
+        let gas = call
+            .estimate_gas()
+            .await
+            .map_err(EvmClientError::from_contract_error)?;
+        let block_gas_limit = self.block_gas_limit().await?;
+        let gas_with_margin = apply_margin(gas, margin_percent, block_gas_limit)?;
+        log::debug!(
+            "Estimated {} gas, {} with margin, block gas limit {}",
+            gas,
+            gas_with_margin,
+            block_gas_limit
+        );
+        Ok(gas_with_margin)
+    }
+}
+
+/// Adds `margin_percent` of headroom to `estimate`, rounded down, without going over
+/// `block_gas_limit`.
+///
+/// Fails if the estimate alone doesn't fit in a block.
+pub fn apply_margin(
+    estimate: U256,
+    margin_percent: u64,
+    block_gas_limit: U256,
+) -> Result<U256, EvmClientError> {
+    if estimate > block_gas_limit {
+        return Err(EvmClientError::GasLimitExceeded {
+            required: estimate,
+            block_gas_limit,
+        });
+    }
+    // A margin too large to compute is over the block gas limit anyway.
+    let gas_with_margin = estimate
+        .checked_mul(U256::from(margin_percent))
+        .map(|margin| margin / 100)
+        .and_then(|margin| estimate.checked_add(margin))
+        .unwrap_or(block_gas_limit);
+    Ok(gas_with_margin.min(block_gas_limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn margin_rounding() {
+        let limit = U256::from(30_000_000u64);
+        let apply = |estimate: u64, percent| apply_margin(estimate.into(), percent, limit).unwrap();
+        assert_eq!(apply(100_000, 20), U256::from(120_000u64));
+        assert_eq!(apply(21, 20), U256::from(25u64));
+        assert_eq!(apply(4, 20), U256::from(4u64));
+        assert_eq!(apply(100_000, 0), U256::from(100_000u64));
+    }
+
+    #[test]
+    fn margin_capped_at_block_gas_limit() {
+        let limit = U256::from(30_000_000u64);
+        assert_eq!(
+            apply_margin(U256::from(29_000_000u64), 20, limit).unwrap(),
+            limit
+        );
+        assert_eq!(apply_margin(limit, 20, limit).unwrap(), limit);
+        // The margin overflows, but the estimate itself fits.
+        assert_eq!(
+            apply_margin(U256::MAX, u64::MAX, U256::MAX).unwrap(),
+            U256::MAX
+        );
+        assert_eq!(
+            apply_margin(U256::MAX / 2, 150, U256::MAX).unwrap(),
+            U256::MAX
+        );
+    }
+
+    #[test]
+    fn estimate_over_block_gas_limit() {
+        let limit = U256::from(30_000_000u64);
+        let estimate = U256::from(30_000_001u64);
+        match apply_margin(estimate, 20, limit) {
+            Err(EvmClientError::GasLimitExceeded {
+                required,
+                block_gas_limit,
+            }) => {
+                assert_eq!(required, estimate);
+                assert_eq!(block_gas_limit, limit);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
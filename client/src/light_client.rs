@@ -0,0 +1,145 @@
+//! Typed reads of the treasury light client state.
+//!
+//! `commitRoots` only grows, so instead of `viewCommitRoots()` the state is read slot by slot
+//! with `eth_getStorageAt`, following the storage layout of `EVMTreasury`:
+//!
+//! | slot | variable |
+//! |------|----------|
+//! | 0 | `ReentrancyGuard._status` |
+//! | 1 | `contractSequence` |
+//! | 2 | `lightClient.heightOffset` |
+//! | 3 | `lightClient.lastHeader` |
+//! | 4 | `lightClient.commitRoots.length`, the roots being at `keccak256(4) + index` |
+//!
+//! The reads of a single call are made at the same block so that they are consistent.
+
+use crate::{EvmClientError, EvmCompatibleChain};
+use ethers::types::{BlockId, BlockNumber, H256, U256};
+use ethers::utils::keccak256;
+use ethers_providers::Middleware;
+use eyre::Error;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use simperby_core::{BlockHeight, Hash256};
+use std::ops::RangeInclusive;
+
+const HEIGHT_OFFSET_SLOT: u64 = 2;
+const COMMIT_ROOTS_SLOT: u64 = 4;
+/// The number of commit roots read concurrently, so that a long history doesn't flood the RPC.
+const MAX_CONCURRENT_READS: usize = 16;
+
+impl EvmCompatibleChain {
+    async fn read_treasury_slot(&self, slot: U256, block: BlockId) -> Result<H256, Error> {
+        let mut location = [0u8; 32];
+        slot.to_big_endian(&mut location);
+        self.provider
+            .get_storage_at(self.treasury_address()?, H256(location), Some(block))
+            .await
+            .map_err(|err| {
+                EvmClientError::Rpc(format!("failed to read treasury slot {}: {}", slot, err))
+                    .into()
+            })
+    }
+
+    /// Pins the reads of a call to the latest block.
+    async fn latest_block_id(&self) -> Result<BlockId, Error> {
+        let number =
+            self.provider.get_block_number().await.map_err(|err| {
+                EvmClientError::Rpc(format!("failed to get block number: {}", err))
+            })?;
+        Ok(BlockId::Number(BlockNumber::Number(number)))
+    }
+
+    /// Returns `heightOffset` and the number of commit roots.
+    async fn read_light_client_bounds(&self, block: BlockId) -> Result<(u64, u64), Error> {
+        let height_offset = self
+            .read_treasury_slot(HEIGHT_OFFSET_SLOT.into(), block)
+            .await?;
+        // `heightOffset` is a `uint64` packed at the low-order end of its slot.
+        let height_offset = U256::from_big_endian(height_offset.as_bytes()).low_u64();
+        let length = self
+            .read_treasury_slot(COMMIT_ROOTS_SLOT.into(), block)
+            .await?;
+        let length = U256::from_big_endian(length.as_bytes());
+        if length > U256::from(u64::MAX) {
+            return Err(EvmClientError::Decode {
+                what: "commit roots",
+                reason: format!("invalid length {}", length),
+            }
+            .into());
+        }
+        Ok((height_offset, length.as_u64()))
+    }
+
+    async fn read_commit_root(&self, index: u64, block: BlockId) -> Result<[u8; 32], Error> {
+        let base = U256::from_big_endian(&keccak256(H256::from_low_u64_be(COMMIT_ROOTS_SLOT)));
+        let root = self
+            .read_treasury_slot(base + U256::from(index), block)
+            .await?;
+        Ok(root.0)
+    }
+
+    /// Returns the raw commit root of the block at `height`, if the light client covers it.
+    pub(crate) async fn commit_root_at(
+        &self,
+        height: BlockHeight,
+    ) -> Result<Option<[u8; 32]>, Error> {
+        let block = self.latest_block_id().await?;
+        let (height_offset, length) = self.read_light_client_bounds(block).await?;
+        match height.checked_sub(height_offset) {
+            Some(index) if index < length => Ok(Some(self.read_commit_root(index, block).await?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the commit roots stored by the light client, the first being of height
+    /// `heightOffset`.
+    ///
+    /// This reads one storage slot per root; prefer [`Self::get_commit_root`] for a single height.
+    pub async fn get_commit_roots(&self) -> Result<Vec<Hash256>, Error> {
+        let block = self.latest_block_id().await?;
+        let (_, length) = self.read_light_client_bounds(block).await?;
+        let roots = stream::iter(0..length)
+            .map(|index| self.read_commit_root(index, block))
+            .buffered(MAX_CONCURRENT_READS)
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(roots.into_iter().map(Hash256::from_array).collect())
+    }
+
+    /// Returns the simperby heights whose transactions the treasury can verify.
+    pub async fn get_light_client_height_range(
+        &self,
+    ) -> Result<RangeInclusive<BlockHeight>, Error> {
+        let block = self.latest_block_id().await?;
+        let (height_offset, length) = self.read_light_client_bounds(block).await?;
+        let end = height_offset
+            .checked_add(length)
+            .ok_or_else(|| EvmClientError::Decode {
+                what: "light client",
+                reason: format!(
+                    "height offset {} with {} commit roots overflows",
+                    height_offset, length
+                ),
+            })?;
+        // The treasury is deployed with the genesis header, so there is always a root.
+        let last_height = end.checked_sub(1).ok_or(EvmClientError::Decode {
+            what: "light client",
+            reason: "no commit root is stored".to_owned(),
+        })?;
+        Ok(height_offset..=last_height)
+    }
+
+    /// Returns the commit root of the block at `height`, or `None` if the light client
+    /// doesn't cover it.
+    pub async fn get_commit_root(&self, height: BlockHeight) -> Result<Option<Hash256>, Error> {
+        Ok(self.commit_root_at(height).await?.map(Hash256::from_array))
+    }
+
+    /// Returns whether `execute` can prove a transaction of the block at `height`.
+    pub async fn is_height_provable(&self, height: BlockHeight) -> Result<bool, Error> {
+        Ok(self
+            .get_light_client_height_range()
+            .await?
+            .contains(&height))
+    }
+}
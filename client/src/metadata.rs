@@ -0,0 +1,163 @@
+//! Token metadata, fetched once per token and cached.
+
+use crate::amount::{format_units, parse_units, AmountError};
+use crate::error::is_contract_rejection;
+use crate::{EvmClientError, EvmCompatibleAddress, EvmCompatibleChain, IERC20, IERC721};
+use ethers::contract::ContractError;
+use ethers::types::{Address, U256};
+use ethers_providers::Middleware;
+use eyre::Error;
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// The metadata of an ERC-20 token, or of ETH for the native token address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FungibleTokenMetadata {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    /// The number of decimals of the display unit. EIP-20 makes `decimals` optional; tokens
+    /// without it are treated as indivisible.
+    pub decimals: u8,
+}
+
+impl FungibleTokenMetadata {
+    fn native() -> Self {
+        FungibleTokenMetadata {
+            name: Some("Ether".to_owned()),
+            symbol: Some("ETH".to_owned()),
+            decimals: 18,
+        }
+    }
+
+    /// Formats an amount in base units for display, such as `1,250.5 USDT`.
+    pub fn format_amount(&self, value: U256) -> String {
+        let amount = format_units(value, self.decimals);
+        match &self.symbol {
+            Some(symbol) => format!("{} {}", amount, symbol),
+            None => amount,
+        }
+    }
+
+    /// Parses a display amount, such as `1,250.5`, into base units.
+    pub fn parse_amount(&self, amount: &str) -> Result<U256, AmountError> {
+        parse_units(amount, self.decimals)
+    }
+}
+
+/// The metadata of an ERC-721 collection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonFungibleTokenMetadata {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+}
+
+/// Token metadata keyed by token address. Metadata doesn't change, so entries are never evicted.
+#[derive(Debug, Default)]
+pub(crate) struct TokenMetadataCache {
+    fungible: RwLock<HashMap<Address, FungibleTokenMetadata>>,
+    non_fungible: RwLock<HashMap<Address, NonFungibleTokenMetadata>>,
+}
+
+/// Treats an optional getter that reverts or returns undecodable data as absent, while still
+/// failing on transport errors so that they aren't cached.
+fn optional_field<T, M: Middleware>(
+    result: Result<T, ContractError<M>>,
+) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if is_contract_rejection(&err) => Ok(None),
+        Err(err) => Err(EvmClientError::Rpc(err.to_string()).into()),
+    }
+}
+
+impl EvmCompatibleChain {
+    /// Returns the name, symbol and decimals of a fungible token, fetching them on first use.
+    pub async fn get_fungible_token_metadata(
+        &self,
+        token: Address,
+    ) -> Result<FungibleTokenMetadata, Error> {
+        if EvmCompatibleAddress::from(token).is_native() {
+            return Ok(FungibleTokenMetadata::native());
+        }
+        let cached = self
+            .token_metadata
+            .fungible
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&token)
+            .cloned();
+        if let Some(metadata) = cached {
+            return Ok(metadata);
+        }
+        let contract = IERC20::new(token, Arc::clone(&self.provider));
+        let name = contract.name();
+        let symbol = contract.symbol();
+        let decimals = contract.decimals();
+        let (name, symbol, decimals) = futures::join!(name.call(), symbol.call(), decimals.call());
+        let decimals = optional_field(decimals)?.unwrap_or_else(|| {
+            log::warn!(
+                "Token {:?} has no decimals, treating it as indivisible",
+                token
+            );
+            0
+        });
+        let metadata = FungibleTokenMetadata {
+            name: optional_field(name)?,
+            symbol: optional_field(symbol)?,
+            decimals,
+        };
+        self.token_metadata
+            .fungible
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(token, metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Returns the name and symbol of an NFT collection, fetching them on first use.
+    pub async fn get_non_fungible_token_metadata(
+        &self,
+        collection: Address,
+    ) -> Result<NonFungibleTokenMetadata, Error> {
+        let cached = self
+            .token_metadata
+            .non_fungible
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&collection)
+            .cloned();
+        if let Some(metadata) = cached {
+            return Ok(metadata);
+        }
+        let contract = IERC721::new(collection, Arc::clone(&self.provider));
+        let name = contract.name();
+        let symbol = contract.symbol();
+        let (name, symbol) = futures::join!(name.call(), symbol.call());
+        let metadata = NonFungibleTokenMetadata {
+            name: optional_field(name)?,
+            symbol: optional_field(symbol)?,
+        };
+        self.token_metadata
+            .non_fungible
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(collection, metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Formats an amount of `token` in base units for display, such as `1,250.5 USDT`.
+    pub async fn format_token_amount(&self, token: Address, value: U256) -> Result<String, Error> {
+        Ok(self
+            .get_fungible_token_metadata(token)
+            .await?
+            .format_amount(value))
+    }
+
+    /// Parses a display amount of `token`, such as `1,250.5`, into base units.
+    pub async fn parse_token_amount(&self, token: Address, amount: &str) -> Result<U256, Error> {
+        let metadata = self.get_fungible_token_metadata(token).await?;
+        Ok(metadata
+            .parse_amount(amount)
+            .map_err(EvmClientError::InvalidAmount)?)
+    }
+}
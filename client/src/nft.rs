@@ -0,0 +1,143 @@
+//! Listing the NFTs the treasury holds.
+
+use crate::error::is_contract_rejection;
+use crate::{EvmClientError, EvmCompatibleChain, EvmProvider, IERC721};
+use ethers::types::{Address, BlockId, BlockNumber, U256};
+use ethers_providers::Middleware;
+use eyre::Error;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// The ERC-165 interface ID of `ERC721Enumerable`.
+const ERC721_ENUMERABLE_INTERFACE_ID: [u8; 4] = [0x78, 0x0e, 0x9d, 0x63];
+/// The default number of blocks covered by one `eth_getLogs` query, within the range hosted
+/// RPCs accept.
+pub const DEFAULT_LOG_BLOCK_RANGE: u64 = 2_000;
+/// The number of tokens read concurrently from an `ERC721Enumerable` collection.
+const MAX_CONCURRENT_READS: usize = 16;
+
+impl EvmCompatibleChain {
+    /// Returns the IDs of the tokens of `collection` the treasury holds at the latest block.
+    pub(crate) async fn treasury_token_ids(
+        &self,
+        collection: Address,
+    ) -> Result<BTreeSet<U256>, Error> {
+        let treasury = self.treasury_address()?;
+        let contract = IERC721::new(collection, Arc::clone(&self.provider));
+        // Every read is pinned to one block so that they agree with each other.
+        let latest = self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(|err| EvmClientError::Rpc(format!("failed to get block number: {}", err)))?
+            .as_u64();
+        let block = BlockId::Number(BlockNumber::Number(latest.into()));
+        let enumerable = match contract
+            .supports_interface(ERC721_ENUMERABLE_INTERFACE_ID)
+            .block(block)
+            .call()
+            .await
+        {
+            Ok(enumerable) => enumerable,
+            // Collections which don't implement ERC-165 at all revert here.
+            Err(err) if is_contract_rejection(&err) => false,
+            Err(err) => return Err(EvmClientError::from_contract_error(err).into()),
+        };
+        if enumerable {
+            enumerate_token_ids(&contract, treasury, block).await
+        } else {
+            self.replay_token_transfers(&contract, treasury, latest)
+                .await
+        }
+    }
+
+    /// Replays every transfer into and out of `owner` up to block `latest`, in the order they
+    /// happened, querying the logs in windows from the treasury's deployment block.
+    async fn replay_token_transfers(
+        &self,
+        contract: &IERC721<EvmProvider>,
+        owner: Address,
+        latest: u64,
+    ) -> Result<BTreeSet<U256>, Error> {
+        let range = self.chain.get_log_block_range().max(1);
+        let mut transfers = Vec::new();
+        let mut from = match self.chain.get_treasury_deploy_block() {
+            Some(block) => block,
+            // Public networks have far too many blocks to scan.
+            None if self.chain.preset().is_some() => {
+                return Err(EvmClientError::TreasuryDeployBlockNotSet.into())
+            }
+            None => {
+                log::warn!("Treasury deploy block is not set; scanning NFT transfers from genesis");
+                0
+            }
+        };
+        while from <= latest {
+            let to = latest.min(from.saturating_add(range - 1));
+            let incoming = contract
+                .transfer_filter()
+                .topic2(owner)
+                .from_block(from)
+                .to_block(to)
+                .query_with_meta()
+                .await
+                .map_err(EvmClientError::from_contract_error)?;
+            let outgoing = contract
+                .transfer_filter()
+                .topic1(owner)
+                .from_block(from)
+                .to_block(to)
+                .query_with_meta()
+                .await
+                .map_err(EvmClientError::from_contract_error)?;
+            transfers.extend(incoming);
+            transfers.extend(outgoing);
+            from = to + 1;
+        }
+        transfers.sort_by_key(|(_, meta)| (meta.block_number, meta.log_index));
+        let mut token_ids = BTreeSet::new();
+        for (transfer, _) in transfers {
+            if transfer.to == owner {
+                token_ids.insert(transfer.token_id);
+            } else {
+                token_ids.remove(&transfer.token_id);
+            }
+        }
+        Ok(token_ids)
+    }
+}
+
+/// Lists the tokens of `owner` through `ERC721Enumerable`, as of `block`.
+async fn enumerate_token_ids(
+    contract: &IERC721<EvmProvider>,
+    owner: Address,
+    block: BlockId,
+) -> Result<BTreeSet<U256>, Error> {
+    let balance = contract
+        .balance_of(owner)
+        .block(block)
+        .call()
+        .await
+        .map_err(EvmClientError::from_contract_error)?;
+    if balance > U256::from(u64::MAX) {
+        return Err(EvmClientError::Decode {
+            what: "NFT balance",
+            reason: format!("invalid balance {}", balance),
+        }
+        .into());
+    }
+    let token_ids = stream::iter(0..balance.as_u64())
+        .map(|index| async move {
+            contract
+                .token_of_owner_by_index(owner, index.into())
+                .block(block)
+                .call()
+                .await
+                .map_err(EvmClientError::from_contract_error)
+        })
+        .buffer_unordered(MAX_CONCURRENT_READS)
+        .try_collect()
+        .await?;
+    Ok(token_ids)
+}
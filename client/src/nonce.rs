@@ -0,0 +1,179 @@
+//! Local nonce allocation for the relayer account.
+
+use crate::{EvmClientError, EvmProvider};
+use async_trait::async_trait;
+use ethers::types::{Address, BlockNumber, U256};
+use ethers_providers::Middleware;
+use eyre::Error;
+use tokio::sync::Mutex;
+
+/// Where a [`NonceManager`] loads the next nonce of an account from.
+#[async_trait]
+pub trait NonceSource: Send + Sync {
+    /// Returns the nonce of the next transaction of `address`, counting the pending ones.
+    async fn pending_nonce(&self, address: Address) -> Result<U256, Error>;
+}
+
+#[async_trait]
+impl NonceSource for EvmProvider {
+    async fn pending_nonce(&self, address: Address) -> Result<U256, Error> {
+        self.get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|err| {
+                EvmClientError::Rpc(format!("failed to get nonce of {:?}: {}", address, err)).into()
+            })
+    }
+}
+
+/// Hands out consecutive nonces to the transactions of one account, so that concurrent writes
+/// don't reuse a nonce and dependent transactions can be sent without waiting for each other.
+///
+/// The next nonce is loaded from the pending transaction count on first use and after
+/// [`NonceManager::resync`], which is called whenever a send fails and the allocated nonce may
+/// have been left unused.
+#[derive(Debug, Default)]
+pub struct NonceManager {
+    next: Mutex<Option<U256>>,
+}
+
+impl NonceManager {
+    /// Allocates `count` consecutive nonces and returns the first one.
+    pub async fn reserve<S: NonceSource + ?Sized>(
+        &self,
+        source: &S,
+        address: Address,
+        count: usize,
+    ) -> Result<U256, Error> {
+        let mut next = self.next.lock().await;
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => source.pending_nonce(address).await?,
+        };
+        *next = Some(nonce + U256::from(count));
+        Ok(nonce)
+    }
+
+    /// Allocates the next nonce.
+    pub async fn next<S: NonceSource + ?Sized>(
+        &self,
+        source: &S,
+        address: Address,
+    ) -> Result<U256, Error> {
+        self.reserve(source, address, 1).await
+    }
+
+    /// Forgets the local nonce so that the next allocation reloads it from the node.
+    pub async fn resync(&self) {
+        *self.next.lock().await = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A node whose pending nonce is set by the test.
+    #[derive(Default)]
+    struct TestSource {
+        nonce: std::sync::Mutex<U256>,
+        failing: AtomicBool,
+        loads: AtomicUsize,
+    }
+
+    impl TestSource {
+        fn new(nonce: u64) -> Self {
+            TestSource {
+                nonce: std::sync::Mutex::new(U256::from(nonce)),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[async_trait]
+    impl NonceSource for TestSource {
+        async fn pending_nonce(&self, _address: Address) -> Result<U256, Error> {
+            // Lets concurrent allocations interleave with the load.
+            tokio::task::yield_now().await;
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(EvmClientError::Rpc("unreachable".to_owned()).into());
+            }
+            Ok(*self.nonce.lock().unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn sequential_reserve() {
+        let source = TestSource::new(5);
+        let manager = NonceManager::default();
+        let address = Address::zero();
+        assert_eq!(
+            manager.next(&source, address).await.unwrap(),
+            U256::from(5u64)
+        );
+        assert_eq!(
+            manager.reserve(&source, address, 3).await.unwrap(),
+            U256::from(6u64)
+        );
+        assert_eq!(
+            manager.next(&source, address).await.unwrap(),
+            U256::from(9u64)
+        );
+        assert_eq!(source.loads.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn resync_after_error() {
+        let source = TestSource::new(5);
+        let manager = NonceManager::default();
+        let address = Address::zero();
+        assert_eq!(
+            manager.next(&source, address).await.unwrap(),
+            U256::from(5u64)
+        );
+        assert_eq!(
+            manager.next(&source, address).await.unwrap(),
+            U256::from(6u64)
+        );
+
+        // The send of nonce 6 failed, so the node still expects it.
+        manager.resync().await;
+        assert_eq!(
+            manager.next(&source, address).await.unwrap(),
+            U256::from(6u64)
+        );
+
+        // A failed load leaves the nonce to be loaded again by the next allocation.
+        manager.resync().await;
+        source.failing.store(true, Ordering::SeqCst);
+        assert!(manager.next(&source, address).await.is_err());
+        source.failing.store(false, Ordering::SeqCst);
+        *source.nonce.lock().unwrap() = U256::from(8u64);
+        assert_eq!(
+            manager.next(&source, address).await.unwrap(),
+            U256::from(8u64)
+        );
+        assert_eq!(source.loads.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_next() {
+        let source = Arc::new(TestSource::new(0));
+        let manager = Arc::new(NonceManager::default());
+        let allocations = (0..32).map(|_| {
+            let source = Arc::clone(&source);
+            let manager = Arc::clone(&manager);
+            tokio::spawn(async move { manager.next(&*source, Address::zero()).await })
+        });
+        let nonces = futures::future::join_all(allocations)
+            .await
+            .into_iter()
+            .map(|nonce| nonce.unwrap().unwrap().as_u64())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(nonces, (0..32).collect());
+        assert_eq!(source.loads.load(Ordering::SeqCst), 1);
+    }
+}
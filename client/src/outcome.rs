@@ -0,0 +1,79 @@
+use crate::error::{EvmClientError, TreasuryRevert};
+use crate::{ITreasuryEvents, RelayerClient, TransactionFees};
+use ethers::abi::RawLog;
+use ethers::contract::{ContractCall, EthLogDecode};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, TransactionReceipt, H256, U256, U64};
+use eyre::Error;
+use simperby_core::BlockHeight;
+
+/// A treasury transaction of the relayer that has been sent but not confirmed yet.
+///
+/// Pass it to `EvmCompatibleChain::wait_for_relayer_transaction` to get its outcome, or to
+/// `EvmCompatibleChain::cancel_relayer_transaction` to replace it with an empty transaction.
+pub struct PendingRelayerTransaction {
+    pub transaction_hash: H256,
+    pub nonce: U256,
+    /// The call, kept to replay it if it reverts.
+    pub(crate) call: ContractCall<RelayerClient, ()>,
+    /// The latest transaction sent with this nonce, resent with bumped fees if it gets stuck.
+    pub(crate) tx: TypedTransaction,
+    /// The height the light client is updated to, if this is a light client update.
+    pub(crate) light_client_height: Option<BlockHeight>,
+    /// The fees of the latest transaction sent with this nonce.
+    pub(crate) fees: TransactionFees,
+    /// The hashes of the transactions replaced by `transaction_hash`, oldest first.
+    pub(crate) replaced: Vec<H256>,
+}
+
+/// The outcome of a transaction that has been mined and confirmed.
+#[derive(Debug, Clone)]
+pub struct TransactionOutcome {
+    pub transaction_hash: H256,
+    pub block_number: Option<u64>,
+    /// `false` if the transaction reverted.
+    pub success: bool,
+    pub gas_used: Option<U256>,
+    /// The treasury events emitted by the transaction, in log order.
+    pub events: Vec<ITreasuryEvents>,
+    /// The decoded reason of a reverted treasury transaction, if it could be recovered.
+    pub revert: Option<TreasuryRevert>,
+}
+
+impl TransactionOutcome {
+    /// Builds the outcome from a receipt, decoding the events emitted by `treasury`.
+    pub fn from_receipt(receipt: &TransactionReceipt, treasury: Option<Address>) -> Self {
+        let events = receipt
+            .logs
+            .iter()
+            .filter(|log| Some(log.address) == treasury)
+            .filter_map(|log| {
+                ITreasuryEvents::decode_log(&RawLog {
+                    topics: log.topics.clone(),
+                    data: log.data.to_vec(),
+                })
+                .ok()
+            })
+            .collect();
+        TransactionOutcome {
+            transaction_hash: receipt.transaction_hash,
+            block_number: receipt.block_number.map(|number| number.as_u64()),
+            success: receipt.status == Some(U64::one()),
+            gas_used: receipt.gas_used,
+            events,
+            revert: None,
+        }
+    }
+
+    /// Returns an error if the transaction reverted.
+    pub fn ensure_success(&self) -> Result<(), Error> {
+        if self.success {
+            return Ok(());
+        }
+        Err(match &self.revert {
+            Some(revert) => EvmClientError::Reverted(revert.clone()),
+            None => EvmClientError::TransactionReverted(self.transaction_hash),
+        }
+        .into())
+    }
+}
@@ -0,0 +1,95 @@
+//! Checks of an execution against the live treasury state, mirroring the `require`s of
+//! `EVMTreasury.execute` that follow the commitment verification.
+
+use crate::amount::decimal_to_u256;
+use crate::error::ExecutionRejection;
+use crate::{EvmClientError, EvmCompatibleAddress, EvmCompatibleChain, IERC721};
+use ethers::types::U256;
+use eyre::Error;
+use simperby_settlement::execution::{Execution, ExecutionMessage};
+use simperby_settlement::SettlementChain;
+use std::sync::Arc;
+
+impl EvmCompatibleChain {
+    /// Returns `EvmClientError::ExecutionRejected` if the treasury would revert `execution`.
+    pub async fn check_execution(&self, execution: &Execution) -> Result<(), Error> {
+        let treasury = self.treasury_address()?;
+        let chain_name = self.get_treasury_chain_name()?;
+        if chain_name != execution.target_chain {
+            return Err(EvmClientError::from(ExecutionRejection::ChainMismatch {
+                execution: execution.target_chain.clone(),
+                treasury: chain_name.to_owned(),
+            })
+            .into());
+        }
+        let contract_sequence = self.get_contract_sequence().await?;
+        if execution.contract_sequence != contract_sequence {
+            return Err(
+                EvmClientError::from(ExecutionRejection::ContractSequenceMismatch {
+                    execution: execution.contract_sequence,
+                    treasury: contract_sequence,
+                })
+                .into(),
+            );
+        }
+
+        match &execution.message {
+            ExecutionMessage::TransferFungibleToken(transfer) => {
+                let token =
+                    EvmCompatibleAddress::from_hex_serialized_vec(&transfer.token_address)?.address;
+                let required =
+                    decimal_to_u256(transfer.amount).map_err(EvmClientError::InvalidAmount)?;
+                let available = self.fungible_token_balance(token, treasury).await?;
+                if available < required {
+                    return Err(
+                        EvmClientError::from(ExecutionRejection::InsufficientBalance {
+                            token,
+                            required,
+                            available,
+                        })
+                        .into(),
+                    );
+                }
+            }
+            ExecutionMessage::TransferNonFungibleToken(transfer) => {
+                let collection =
+                    EvmCompatibleAddress::from_hex_serialized_vec(&transfer.collection_address)?
+                        .address;
+                if transfer.token_index.data.len() > 32 {
+                    return Err(EvmClientError::Decode {
+                        what: "token index",
+                        reason: format!(
+                            "{} bytes is longer than 32",
+                            transfer.token_index.data.len()
+                        ),
+                    }
+                    .into());
+                }
+                let token_id = U256::from_big_endian(&transfer.token_index.data);
+                // `ownerOf` reverts for a token that doesn't exist.
+                let owner = match IERC721::new(collection, Arc::clone(&self.provider))
+                    .owner_of(token_id)
+                    .call()
+                    .await
+                    .map_err(EvmClientError::from_contract_error)
+                {
+                    Ok(owner) => Some(owner),
+                    Err(EvmClientError::Reverted(_)) => None,
+                    Err(err) => return Err(err.into()),
+                };
+                if owner != Some(treasury) {
+                    return Err(EvmClientError::from(ExecutionRejection::NotTokenOwner {
+                        collection,
+                        token_id,
+                        owner,
+                    })
+                    .into());
+                }
+            }
+            ExecutionMessage::Dummy { .. } => {
+                return Err(EvmClientError::from(ExecutionRejection::UnsupportedMessage).into());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Known networks and the defaults the client derives from them.
+
+use crate::ChainType;
+use std::time::Duration;
+
+/// The bounds of the receipt polling interval derived from the block time.
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(7);
+/// The number of blocks a relayer transaction may stay unmined before it is replaced.
+const REPLACEMENT_TIMEOUT_BLOCKS: u32 = 15;
+/// The lower bound of the replacement timeout, for chains with sub-second blocks.
+const MIN_REPLACEMENT_TIMEOUT: Duration = Duration::from_secs(30);
+/// How far behind the other RPC endpoints one may be before it is considered stale.
+const MAX_ENDPOINT_LAG: Duration = Duration::from_secs(60);
+
+/// The properties of a known network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainPreset {
+    pub name: &'static str,
+    /// The chain ID the RPC endpoint must report.
+    pub chain_id: u64,
+    /// Whether the network supports EIP-1559 transactions.
+    pub eip1559: bool,
+    /// The recommended number of confirmations before a transaction is considered final.
+    pub confirmations: usize,
+    /// The average block time.
+    pub block_time: Duration,
+}
+
+impl ChainPreset {
+    const fn new(
+        name: &'static str,
+        chain_id: u64,
+        eip1559: bool,
+        confirmations: usize,
+        block_time_millis: u64,
+    ) -> Self {
+        ChainPreset {
+            name,
+            chain_id,
+            eip1559,
+            confirmations,
+            block_time: Duration::from_millis(block_time_millis),
+        }
+    }
+
+    /// The interval receipts are polled at, about once a block.
+    pub fn poll_interval(&self) -> Duration {
+        self.block_time.clamp(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL)
+    }
+
+    /// The number of blocks an RPC endpoint may lag behind the others.
+    pub fn max_block_lag(&self) -> u64 {
+        (MAX_ENDPOINT_LAG.as_millis() / self.block_time.as_millis().max(1)) as u64
+    }
+
+    /// The time after which a relayer transaction is considered stuck.
+    pub fn replacement_timeout(&self) -> Duration {
+        (self.block_time * REPLACEMENT_TIMEOUT_BLOCKS).max(MIN_REPLACEMENT_TIMEOUT)
+    }
+}
+
+impl ChainType {
+    /// Returns the preset of a known network, or `None` for [`ChainType::Other`].
+    pub fn preset(&self) -> Option<ChainPreset> {
+        let preset = match self {
+            ChainType::Ethereum(_) => ChainPreset::new("Ethereum", 1, true, 3, 12_000),
+            ChainType::Goerli(_) => ChainPreset::new("Goerli", 5, true, 2, 12_000),
+            ChainType::Sepolia(_) => ChainPreset::new("Sepolia", 11_155_111, true, 2, 12_000),
+            ChainType::Holesky(_) => ChainPreset::new("Holesky", 17_000, true, 2, 12_000),
+            ChainType::Polygon(_) => ChainPreset::new("Polygon", 137, true, 32, 2_000),
+            ChainType::Arbitrum(_) => ChainPreset::new("Arbitrum", 42_161, true, 1, 250),
+            ChainType::Optimism(_) => ChainPreset::new("Optimism", 10, true, 1, 2_000),
+            ChainType::Base(_) => ChainPreset::new("Base", 8_453, true, 1, 2_000),
+            // BNB Chain accepts EIP-1559 transactions but has no base fee to price them with.
+            ChainType::BnbChain(_) => ChainPreset::new("BNB Chain", 56, false, 15, 3_000),
+            // The default chain ID of both Anvil and Hardhat Network.
+            ChainType::Local(_) => ChainPreset::new("Local", 31_337, true, 1, 1_000),
+            ChainType::Other(_) => return None,
+        };
+        Some(preset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChainConfigs, FeeStrategy};
+
+    #[test]
+    fn preset_defaults() {
+        let ethereum = ChainType::Ethereum(ChainConfigs::new("http://localhost:8545"));
+        assert_eq!(ethereum.get_expected_chain_id(), Some(1));
+        assert_eq!(ethereum.get_confirmations(), 3);
+        assert_eq!(ethereum.get_fee_strategy(), FeeStrategy::eip1559());
+        assert_eq!(
+            ethereum.get_replacement_policy().timeout,
+            Duration::from_secs(180)
+        );
+        assert_eq!(ethereum.get_poll_interval(), Some(MAX_POLL_INTERVAL));
+
+        let arbitrum = ChainType::Arbitrum(ChainConfigs::new("http://localhost:8545"));
+        assert_eq!(
+            arbitrum.get_replacement_policy().timeout,
+            MIN_REPLACEMENT_TIMEOUT
+        );
+        assert_eq!(arbitrum.get_poll_interval(), Some(MIN_POLL_INTERVAL));
+
+        let bnb = ChainType::BnbChain(
+            ChainConfigs::new("http://localhost:8545")
+                .with_confirmations(1)
+                .with_expected_chain_id(97),
+        );
+        assert_eq!(bnb.get_fee_strategy(), FeeStrategy::legacy());
+        assert_eq!(bnb.get_confirmations(), 1);
+        assert_eq!(bnb.get_expected_chain_id(), Some(97));
+
+        let other = ChainType::Other(ChainConfigs::new("http://localhost:8545"));
+        assert_eq!(other.get_expected_chain_id(), None);
+        assert_eq!(other.get_poll_interval(), None);
+        assert_eq!(other.get_chain_name(), "Unknown");
+    }
+}
@@ -0,0 +1,82 @@
+//! Relaying execution transactions of a finalized simperby block to the treasury.
+
+use crate::{EvmClientError, EvmCompatibleChain, TransactionOutcome, TreasuryRevert};
+use eyre::Error;
+use simperby_core::merkle_tree::OneshotMerkleTree;
+use simperby_core::{BlockHeader, BlockHeight, Commit, FinalizationProof, ToHash256, Transaction};
+
+impl EvmCompatibleChain {
+    /// Executes `transaction` of the block at `block_height` and waits for it to be confirmed.
+    ///
+    /// `block_commits` are the commits of that block, from its agenda to its last commit before
+    /// the block header, which are used to build the merkle proof of `transaction`.
+    ///
+    /// If the light client doesn't cover `block_height` yet, it is updated with
+    /// `finalized_header`, which must then be the header of that block with its proof. The update
+    /// and the execution are sent back to back. The contract sequence and the other treasury
+    /// state the execution depends on are checked by [`Self::send_execute`] before sending.
+    pub async fn relay_execution(
+        &self,
+        block_commits: &[Commit],
+        transaction: Transaction,
+        block_height: BlockHeight,
+        finalized_header: Option<(BlockHeader, FinalizationProof)>,
+    ) -> Result<TransactionOutcome, Error> {
+        let merkle_tree =
+            OneshotMerkleTree::create(block_commits.iter().map(|c| c.to_hash256()).collect());
+        let merkle_proof = merkle_tree
+            .create_merkle_proof(transaction.to_hash256())
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "Transaction {:?} is not in the commits of block {}",
+                    transaction.to_hash256(),
+                    block_height
+                )
+            })?;
+
+        let height_range = self.get_light_client_height_range().await?;
+        if block_height < *height_range.start() {
+            return Err(EvmClientError::Reverted(TreasuryRevert::InvalidCommitmentHeight).into());
+        }
+        let light_client_height = *height_range.end();
+        let mut update = None;
+        if light_client_height < block_height {
+            let Some((header, proof)) = finalized_header else {
+                return Err(EvmClientError::Reverted(TreasuryRevert::InvalidCommitmentHeight).into());
+            };
+            if header.height != block_height {
+                return Err(eyre::eyre!(
+                    "Finalized header is of height {}, not {}",
+                    header.height,
+                    block_height
+                ));
+            }
+            if BlockHeader::calculate_commit_merkle_root(block_commits) != header.commit_merkle_root
+            {
+                return Err(
+                    EvmClientError::Reverted(TreasuryRevert::MerkleProofVerificationFail).into(),
+                );
+            }
+            log::info!(
+                "Updating light client from height {} to {} before executing",
+                light_client_height,
+                block_height
+            );
+            update = Some(
+                self.send_update_treasury_light_client(header, proof)
+                    .await?,
+            );
+        }
+
+        // The execution is sent right after the update, without waiting for it to be mined.
+        let execution = self
+            .send_execute(transaction, block_height, merkle_proof)
+            .await;
+        if let Some(update) = update {
+            self.wait_for_relayer_transaction(update)
+                .await?
+                .ensure_success()?;
+        }
+        self.wait_for_relayer_transaction(execution?).await
+    }
+}
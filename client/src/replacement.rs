@@ -0,0 +1,253 @@
+//! Replacing relayer transactions that stay in the mempool.
+
+use crate::{
+    EvmClientError, EvmCompatibleChain, PendingRelayerTransaction, RelayerClient, TransactionFees,
+    TransactionOutcome,
+};
+use ethers::contract::ContractError;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{BlockNumber, TxHash, U256};
+use ethers_providers::Middleware;
+use eyre::Error;
+use std::sync::PoisonError;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// The gas of a plain ETH transfer, which cancels a transaction by replacing it.
+const CANCELLATION_GAS: u64 = 21_000;
+
+/// When and how a pending relayer transaction is replaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplacementPolicy {
+    /// How long a transaction may stay unmined before it is replaced.
+    pub timeout: Duration,
+    /// How much the fees of a replacement are raised, in percent. Nodes reject replacements
+    /// raising the fees by less than 10%.
+    pub fee_bump_percent: u64,
+    /// The number of replacements after which the last one is awaited until it or another
+    /// transaction with its nonce is mined.
+    pub max_replacements: usize,
+}
+
+impl Default for ReplacementPolicy {
+    fn default() -> Self {
+        ReplacementPolicy {
+            timeout: Duration::from_secs(180),
+            fee_bump_percent: 20,
+            max_replacements: 5,
+        }
+    }
+}
+
+impl EvmCompatibleChain {
+    /// Polls the receipts of `hashes` until one of them is mined or `deadline` passes.
+    async fn wait_for_any(
+        &self,
+        hashes: &[TxHash],
+        deadline: Instant,
+    ) -> Result<Option<TxHash>, Error> {
+        loop {
+            for hash in hashes {
+                let receipt =
+                    self.provider
+                        .get_transaction_receipt(*hash)
+                        .await
+                        .map_err(|err| {
+                            EvmClientError::Rpc(format!(
+                                "failed to get receipt of {:?}: {}",
+                                hash, err
+                            ))
+                        })?;
+                if receipt.map_or(false, |receipt| receipt.block_number.is_some()) {
+                    return Ok(Some(*hash));
+                }
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(self.provider.get_interval()).await;
+        }
+    }
+
+    /// Waits until one of the transactions sent with the nonce of `pending` is mined, replacing
+    /// the latest with bumped fees whenever the replacement timeout elapses.
+    ///
+    /// Returns the hash of the mined transaction, or [`EvmClientError::NonceConsumed`] if another
+    /// transaction took the nonce.
+    pub(crate) async fn wait_for_inclusion(
+        &self,
+        pending: &mut PendingRelayerTransaction,
+    ) -> Result<TxHash, Error> {
+        let policy = self.chain.get_replacement_policy();
+        let mut replacements = 0;
+        loop {
+            let deadline = Instant::now() + policy.timeout;
+            if let Some(hash) = self.wait_for_any(&pending.sent_hashes(), deadline).await? {
+                return Ok(hash);
+            }
+            if self.is_nonce_consumed(pending).await? {
+                // One of the transactions may have been mined right after the last poll.
+                let hashes = pending.sent_hashes();
+                if let Some(hash) = self.wait_for_any(&hashes, Instant::now()).await? {
+                    return Ok(hash);
+                }
+                // Later nonces may have been allocated on top of the consumed one.
+                self.nonce_manager.resync().await;
+                if let Some(height) = pending.light_client_height {
+                    self.pending_commit_roots
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .remove(&height);
+                }
+                return Err(EvmClientError::NonceConsumed {
+                    nonce: pending.nonce,
+                    transaction_hash: pending.transaction_hash,
+                }
+                .into());
+            }
+            // Past the last replacement, the latest transaction is awaited indefinitely.
+            if replacements < policy.max_replacements {
+                if self.replace(pending, policy.fee_bump_percent).await? {
+                    replacements += 1;
+                } else {
+                    replacements = policy.max_replacements;
+                }
+            }
+        }
+    }
+
+    /// Whether a transaction with the nonce of `pending` has been mined.
+    async fn is_nonce_consumed(&self, pending: &PendingRelayerTransaction) -> Result<bool, Error> {
+        let relayer = self
+            .relayer_address()
+            .ok_or(EvmClientError::RelayerNotSet)?;
+        let mined = self
+            .provider
+            .get_transaction_count(relayer, Some(BlockNumber::Latest.into()))
+            .await
+            .map_err(|err| {
+                EvmClientError::Rpc(format!("failed to get nonce of {:?}: {}", relayer, err))
+            })?;
+        Ok(mined > pending.nonce)
+    }
+
+    /// Computes the fees replacing `pending`, or logs why there are none.
+    async fn replacement_fees(
+        &self,
+        pending: &PendingRelayerTransaction,
+        fee_bump_percent: u64,
+    ) -> Result<Option<TransactionFees>, Error> {
+        let strategy = self.chain.get_fee_strategy();
+        let current = self.transaction_fees().await?;
+        let fees = strategy.replacement_fees(&pending.fees, &current, fee_bump_percent);
+        if fees.is_none() {
+            log::warn!(
+                "Fees of transaction {:?} (nonce {}) can't be raised within the configured caps",
+                pending.transaction_hash,
+                pending.nonce
+            );
+        }
+        Ok(fees)
+    }
+
+    /// Signs and sends a transaction of the relayer.
+    async fn send_relayer_transaction(&self, tx: TypedTransaction) -> Result<TxHash, Error> {
+        let client = self.relayer_client()?;
+        let sent = client.send_transaction(tx, None).await.map(|sent| *sent);
+        sent.map_err(|err| {
+            let err = ContractError::<RelayerClient>::from_middleware_error(err);
+            EvmClientError::from_contract_error(err).into()
+        })
+    }
+
+    /// Resends the latest transaction of `pending` with the same nonce and bumped fees.
+    ///
+    /// Returns `false` if the fees can't be raised any further within the caps.
+    async fn replace(
+        &self,
+        pending: &mut PendingRelayerTransaction,
+        fee_bump_percent: u64,
+    ) -> Result<bool, Error> {
+        // The endpoints are checked by the pool before serving, but the replacement is signed
+        // again and must not be if the chain ID was found wrong since the original was sent.
+        self.ensure_chain_id()?;
+        let Some(fees) = self.replacement_fees(pending, fee_bump_percent).await? else {
+            return Ok(false);
+        };
+        let mut tx = pending.tx.clone();
+        fees.apply(&mut tx);
+        match self.send_relayer_transaction(tx.clone()).await {
+            Ok(transaction_hash) => {
+                log::warn!(
+                    "Replaced stuck transaction {:?} with {:?} (nonce {})",
+                    pending.transaction_hash,
+                    transaction_hash,
+                    pending.nonce
+                );
+                pending.replaced.push(pending.transaction_hash);
+                pending.transaction_hash = transaction_hash;
+                pending.tx = tx;
+                pending.fees = fees;
+            }
+            // The transaction may have been mined meanwhile, which the next poll finds out.
+            Err(err) => log::warn!(
+                "Failed to replace transaction {:?}: {}",
+                pending.transaction_hash,
+                err
+            ),
+        }
+        Ok(true)
+    }
+
+    /// Cancels a pending relayer transaction by replacing it with an empty transfer to the
+    /// relayer itself, and waits for either to be mined. The cancellation is itself replaced
+    /// if it gets stuck, as by [`Self::wait_for_relayer_transaction`].
+    ///
+    /// The outcome is of whichever transaction was mined; compare its hash with
+    /// `pending.transaction_hash` to know if the cancellation came too late.
+    pub async fn cancel_relayer_transaction(
+        &self,
+        pending: PendingRelayerTransaction,
+    ) -> Result<TransactionOutcome, Error> {
+        let relayer = self.relayer_client()?.address();
+        let policy = self.chain.get_replacement_policy();
+        let fees = self
+            .replacement_fees(&pending, policy.fee_bump_percent)
+            .await?
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "Failed to cancel transaction {:?}: its fees are at the caps",
+                    pending.transaction_hash
+                )
+            })?;
+        let mut tx = fees.value_transfer(relayer, U256::zero());
+        tx.set_from(relayer)
+            .set_gas(CANCELLATION_GAS)
+            .set_nonce(pending.nonce);
+        let cancellation = self.send_relayer_transaction(tx.clone()).await?;
+        log::info!(
+            "Cancelling transaction {:?} with {:?} (nonce {})",
+            pending.transaction_hash,
+            cancellation,
+            pending.nonce
+        );
+        let replaced = pending.sent_hashes();
+        self.wait_for_relayer_transaction(PendingRelayerTransaction {
+            transaction_hash: cancellation,
+            tx,
+            fees,
+            replaced,
+            ..pending
+        })
+        .await
+    }
+}
+
+impl PendingRelayerTransaction {
+    /// Returns the hashes of every transaction sent with this nonce, the latest last.
+    pub fn sent_hashes(&self) -> Vec<TxHash> {
+        let mut hashes = self.replaced.clone();
+        hashes.push(self.transaction_hash);
+        hashes
+    }
+}
@@ -0,0 +1,511 @@
+//! A JSON-RPC transport spreading requests over several endpoints of the same chain.
+
+use async_trait::async_trait;
+use ethers::types::{U256, U64};
+use ethers_providers::{
+    Http, HttpClientError, JsonRpcClient, JsonRpcError, ProviderError, RpcError,
+};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// The weight of the latest request in the moving averages of latency and error rate.
+const SMOOTHING: f64 = 0.3;
+/// How much a fully failing endpoint's latency is inflated in its score.
+const ERROR_PENALTY: f64 = 10.0;
+
+/// When endpoints are marked down and probed again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcPoolConfig {
+    /// The number of consecutive transport failures after which an endpoint is marked down.
+    pub max_failures: u32,
+    /// How long an endpoint stays down before it is tried again.
+    pub down_duration: Duration,
+    /// How often every endpoint is probed for its chain ID and latest block.
+    pub probe_interval: Duration,
+    /// How many blocks an endpoint may lag behind the others before it is marked down.
+    pub max_block_lag: u64,
+    /// How long a request may take before it counts as a transport failure.
+    pub request_timeout: Duration,
+    /// How long an endpoint may take to answer a probe, so that a hung one doesn't hold up
+    /// the request which triggered the probe.
+    pub probe_timeout: Duration,
+}
+
+impl Default for RpcPoolConfig {
+    fn default() -> Self {
+        RpcPoolConfig {
+            max_failures: 3,
+            down_duration: Duration::from_secs(30),
+            probe_interval: Duration::from_secs(30),
+            max_block_lag: 5,
+            request_timeout: Duration::from_secs(30),
+            probe_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RpcPoolError {
+    #[error("no RPC endpoint is configured")]
+    NoEndpoints,
+    #[error("no RPC endpoint serves chain {0}")]
+    WrongChain(u64),
+    #[error("failed to serialize request parameters: {0}")]
+    Serialize(serde_json::Error),
+    #[error("{url}: {source}")]
+    Endpoint {
+        url: String,
+        #[source]
+        source: HttpClientError,
+    },
+}
+
+impl RpcError for RpcPoolError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            RpcPoolError::Endpoint { source, .. } => source.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            RpcPoolError::Serialize(err) => Some(err),
+            RpcPoolError::Endpoint { source, .. } => source.as_serde_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<RpcPoolError> for ProviderError {
+    fn from(err: RpcPoolError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(err))
+    }
+}
+
+#[derive(Debug, Default)]
+struct EndpointHealth {
+    /// The moving average of the request latency, `None` before the first success.
+    latency: Option<Duration>,
+    /// The moving average of the transport failure rate, from 0 to 1.
+    error_rate: f64,
+    consecutive_failures: u32,
+    down_until: Option<Instant>,
+    latest_block: Option<u64>,
+    /// The chain ID the endpoint reported at the last check.
+    chain_id: Option<u64>,
+}
+
+impl EndpointHealth {
+    /// Lower is better. Endpoints which never succeeded score as if they had the `unmeasured`
+    /// latency, so that their failures still count against them.
+    fn score(&self, unmeasured: Duration) -> f64 {
+        let latency = self.latency.unwrap_or(unmeasured).as_secs_f64();
+        latency * (1.0 + ERROR_PENALTY * self.error_rate)
+    }
+
+    fn is_down(&self, now: Instant) -> bool {
+        self.down_until.map_or(false, |until| now < until)
+    }
+
+    fn record_success(&mut self, latency: Duration) {
+        self.latency = Some(match self.latency {
+            Some(average) => average.mul_f64(1.0 - SMOOTHING) + latency.mul_f64(SMOOTHING),
+            None => latency,
+        });
+        self.error_rate *= 1.0 - SMOOTHING;
+        self.consecutive_failures = 0;
+        self.down_until = None;
+    }
+
+    /// Records the chain ID an endpoint reported, logging when it isn't `expected`.
+    fn record_chain_id(&mut self, url: &str, reported: u64, expected: u64) {
+        if reported != expected {
+            log::error!(
+                "RPC endpoint {} reports chain id {} but {} is expected; it won't be used again",
+                url,
+                reported,
+                expected
+            );
+        }
+        self.chain_id = Some(reported);
+    }
+
+    fn record_failure(&mut self, config: &RpcPoolConfig) {
+        self.error_rate = self.error_rate * (1.0 - SMOOTHING) + SMOOTHING;
+        self.consecutive_failures += 1;
+        if self.consecutive_failures >= config.max_failures {
+            self.down_until = Some(Instant::now() + config.down_duration);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    transport: Http,
+    health: Mutex<EndpointHealth>,
+}
+
+impl Endpoint {
+    fn health(&self) -> std::sync::MutexGuard<'_, EndpointHealth> {
+        self.health.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Routes each request to the healthiest endpoint, failing over to the next one on transport
+/// errors.
+///
+/// Endpoints are ranked by their average latency, inflated by their recent error rate. One
+/// failing repeatedly, or lagging behind the others' latest block, is skipped until
+/// [`RpcPoolConfig::down_duration`] passes, unless every endpoint is down. The latest blocks are
+/// probed again every [`RpcPoolConfig::probe_interval`], on the next request, each endpoint
+/// within [`RpcPoolConfig::probe_timeout`].
+///
+/// JSON-RPC error responses, such as reverts, are returned as is: the endpoint is healthy, and
+/// another one would answer the same.
+///
+/// Once the chain ID is set with [`RpcPool::set_chain_id`], every endpoint is checked to report
+/// it before serving its first request and at every probe; one of another chain is never used.
+#[derive(Debug)]
+pub struct RpcPool {
+    endpoints: Vec<Endpoint>,
+    config: RpcPoolConfig,
+    last_probe: Mutex<Option<Instant>>,
+    chain_id: Mutex<Option<u64>>,
+}
+
+impl RpcPool {
+    pub fn new(
+        urls: impl IntoIterator<Item = impl Into<String>>,
+        config: RpcPoolConfig,
+    ) -> Result<Self, eyre::Error> {
+        let endpoints = urls
+            .into_iter()
+            .map(|url| {
+                let url = url.into();
+                let parsed =
+                    Url::parse(&url).map_err(|e| eyre::eyre!("Invalid RPC URL {}: {}", url, e))?;
+                let client = reqwest::Client::builder()
+                    .timeout(config.request_timeout)
+                    .build()
+                    .map_err(|e| eyre::eyre!("Failed to build HTTP client: {}", e))?;
+                let transport = Http::new_with_client(parsed, client);
+                Ok(Endpoint {
+                    url,
+                    transport,
+                    health: Default::default(),
+                })
+            })
+            .collect::<Result<Vec<_>, eyre::Error>>()?;
+        if endpoints.is_empty() {
+            return Err(RpcPoolError::NoEndpoints.into());
+        }
+        Ok(RpcPool {
+            endpoints,
+            config,
+            last_probe: Mutex::new(None),
+            chain_id: Mutex::new(None),
+        })
+    }
+
+    /// Sets the chain ID every endpoint must report, and probes them again on the next request.
+    pub fn set_chain_id(&self, chain_id: u64) {
+        *self.chain_id.lock().unwrap_or_else(PoisonError::into_inner) = Some(chain_id);
+        *self
+            .last_probe
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    fn chain_id(&self) -> Option<u64> {
+        *self.chain_id.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the URLs of the endpoints currently marked down.
+    pub fn down_endpoints(&self) -> Vec<&str> {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .filter(|endpoint| endpoint.health().is_down(now))
+            .map(|endpoint| endpoint.url.as_str())
+            .collect()
+    }
+
+    /// Returns the endpoints not known to serve another chain. Those which do are never used
+    /// again.
+    fn serving_endpoints(&self) -> impl Iterator<Item = &Endpoint> {
+        let chain_id = self.chain_id();
+        self.endpoints.iter().filter(move |endpoint| {
+            let reported = endpoint.health().chain_id;
+            chain_id.is_none() || reported.is_none() || reported == chain_id
+        })
+    }
+
+    /// Returns the endpoints in the order they should be tried.
+    fn ranked(&self) -> Vec<&Endpoint> {
+        let now = Instant::now();
+        // Unmeasured endpoints are assumed to be as slow as the slowest measured one.
+        let unmeasured = self
+            .serving_endpoints()
+            .filter_map(|endpoint| endpoint.health().latency)
+            .max()
+            .unwrap_or(self.config.request_timeout);
+        let mut ranked = self
+            .serving_endpoints()
+            .map(|endpoint| {
+                let health = endpoint.health();
+                (health.is_down(now), health.score(unmeasured), endpoint)
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(a_down, a_score, _), (b_down, b_score, _)| {
+            a_down.cmp(b_down).then(a_score.total_cmp(b_score))
+        });
+        ranked
+            .into_iter()
+            .map(|(_, _, endpoint)| endpoint)
+            .collect()
+    }
+
+    /// Reads the chain ID and the latest block of every endpoint, marking down those which fail
+    /// or lag behind.
+    pub async fn probe(&self) {
+        *self
+            .last_probe
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+        let chain_id = self.chain_id();
+        let probes = self.serving_endpoints().map(|endpoint| async move {
+            let start = Instant::now();
+            let result = tokio::time::timeout(self.config.probe_timeout, async {
+                let reported = match chain_id {
+                    Some(_) => Some(
+                        endpoint
+                            .transport
+                            .request::<_, U256>("eth_chainId", ())
+                            .await?
+                            .low_u64(),
+                    ),
+                    None => None,
+                };
+                let block = endpoint
+                    .transport
+                    .request::<_, U64>("eth_blockNumber", ())
+                    .await?;
+                Ok::<_, HttpClientError>((reported, block.as_u64()))
+            })
+            .await;
+            let mut health = endpoint.health();
+            match result {
+                Ok(Ok((reported, block))) => {
+                    health.record_success(start.elapsed());
+                    health.latest_block = Some(block);
+                    if let (Some(reported), Some(expected)) = (reported, chain_id) {
+                        health.record_chain_id(&endpoint.url, reported, expected);
+                    }
+                }
+                Ok(Err(err)) => {
+                    log::warn!("RPC endpoint {} failed to probe: {}", endpoint.url, err);
+                    health.record_failure(&self.config);
+                }
+                Err(_) => {
+                    log::warn!("RPC endpoint {} timed out on probe", endpoint.url);
+                    health.record_failure(&self.config);
+                }
+            }
+        });
+        futures::future::join_all(probes).await;
+
+        let now = Instant::now();
+        let latest = self
+            .serving_endpoints()
+            .filter_map(|endpoint| endpoint.health().latest_block)
+            .max();
+        let Some(latest) = latest else {
+            return;
+        };
+        for endpoint in self.serving_endpoints() {
+            let mut health = endpoint.health();
+            let lag = latest - health.latest_block.unwrap_or(latest);
+            if lag > self.config.max_block_lag && !health.is_down(now) {
+                log::warn!(
+                    "RPC endpoint {} is {} blocks behind, marking it down",
+                    endpoint.url,
+                    lag
+                );
+                health.down_until = Some(now + self.config.down_duration);
+            }
+        }
+    }
+
+    /// Reads the chain ID of an endpoint which hasn't reported it yet, returning whether it may
+    /// serve requests.
+    async fn check_chain_id(&self, endpoint: &Endpoint) -> Result<bool, HttpClientError> {
+        let Some(chain_id) = self.chain_id() else {
+            return Ok(true);
+        };
+        if let Some(reported) = endpoint.health().chain_id {
+            return Ok(reported == chain_id);
+        }
+        let reported = endpoint
+            .transport
+            .request::<_, U256>("eth_chainId", ())
+            .await?
+            .low_u64();
+        endpoint
+            .health()
+            .record_chain_id(&endpoint.url, reported, chain_id);
+        Ok(reported == chain_id)
+    }
+
+    fn is_probe_due(&self) -> bool {
+        let mut last_probe = self
+            .last_probe
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let due = last_probe.map_or(true, |last| last.elapsed() >= self.config.probe_interval);
+        if due {
+            // Claimed here so that concurrent requests don't probe as well.
+            *last_probe = Some(Instant::now());
+        }
+        due
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for RpcPool {
+    type Error = RpcPoolError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        if self.endpoints.len() > 1 && self.is_probe_due() {
+            self.probe().await;
+        }
+        // Serialized once so that the request can be retried on another endpoint. Resending a
+        // raw transaction is harmless: it has the same hash wherever it lands.
+        let params = serde_json::to_value(params).map_err(RpcPoolError::Serialize)?;
+        let ranked = self.ranked();
+        let mut last_error = match self.chain_id() {
+            Some(chain_id) if ranked.is_empty() => RpcPoolError::WrongChain(chain_id),
+            _ => RpcPoolError::NoEndpoints,
+        };
+        for endpoint in ranked {
+            match self.check_chain_id(endpoint).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    last_error = RpcPoolError::WrongChain(self.chain_id().unwrap_or_default());
+                    continue;
+                }
+                Err(err) => {
+                    log::warn!(
+                        "RPC endpoint {} failed on eth_chainId: {}",
+                        endpoint.url,
+                        err
+                    );
+                    endpoint.health().record_failure(&self.config);
+                    last_error = RpcPoolError::Endpoint {
+                        url: endpoint.url.clone(),
+                        source: err,
+                    };
+                    continue;
+                }
+            }
+            let start = Instant::now();
+            match endpoint.transport.request::<_, R>(method, &params).await {
+                Ok(response) => {
+                    endpoint.health().record_success(start.elapsed());
+                    return Ok(response);
+                }
+                Err(err @ HttpClientError::JsonRpcError(_)) => {
+                    endpoint.health().record_success(start.elapsed());
+                    return Err(RpcPoolError::Endpoint {
+                        url: endpoint.url.clone(),
+                        source: err,
+                    });
+                }
+                Err(err) => {
+                    log::warn!(
+                        "RPC endpoint {} failed on {}: {}",
+                        endpoint.url,
+                        method,
+                        err
+                    );
+                    endpoint.health().record_failure(&self.config);
+                    last_error = RpcPoolError::Endpoint {
+                        url: endpoint.url.clone(),
+                        source: err,
+                    };
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rank_endpoints() {
+        let config = RpcPoolConfig::default();
+        let pool = RpcPool::new(
+            [
+                "http://a:8545",
+                "http://b:8545",
+                "http://c:8545",
+                "http://d:8545",
+            ],
+            config,
+        )
+        .unwrap();
+        pool.endpoints[0]
+            .health()
+            .record_success(Duration::from_millis(300));
+        pool.endpoints[1]
+            .health()
+            .record_success(Duration::from_millis(100));
+        pool.endpoints[2]
+            .health()
+            .record_success(Duration::from_millis(50));
+        for _ in 0..pool.config.max_failures {
+            pool.endpoints[2].health().record_failure(&pool.config);
+        }
+        // Failed without ever succeeding, but not down yet.
+        pool.endpoints[3].health().record_failure(&pool.config);
+        let ranked = pool
+            .ranked()
+            .into_iter()
+            .map(|endpoint| endpoint.url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranked,
+            [
+                "http://b:8545",
+                "http://a:8545",
+                "http://d:8545",
+                "http://c:8545"
+            ]
+        );
+        assert_eq!(pool.down_endpoints(), ["http://c:8545"]);
+
+        pool.set_chain_id(1);
+        pool.endpoints[1]
+            .health()
+            .record_chain_id("http://b:8545", 5, 1);
+        let ranked = pool
+            .ranked()
+            .into_iter()
+            .map(|endpoint| endpoint.url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ranked, ["http://a:8545", "http://d:8545", "http://c:8545"]);
+        assert!(RpcPool::new(Vec::<String>::new(), RpcPoolConfig::default()).is_err());
+    }
+}
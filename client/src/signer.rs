@@ -0,0 +1,87 @@
+use ethers::signers::{coins_bip39::English, LocalWallet, MnemonicBuilder, Signer};
+use ethers::types::Address;
+use ethers_core::k256::ecdsa::SigningKey;
+use eyre::Error;
+use std::fmt;
+use std::path::Path;
+
+const PRIVATE_KEY_BYTES: usize = 32;
+
+/// The key the relayer uses to sign treasury transactions.
+///
+/// The secret is loaded at runtime, so it never ends up in the compiled binary
+/// and a single build can serve several relayer identities.
+#[derive(Clone, PartialEq, Eq)]
+pub enum RelayerSigner {
+    /// A BIP-39 mnemonic phrase. The first account of the default derivation path is used.
+    Mnemonic(String),
+    /// A raw secp256k1 private key.
+    PrivateKey(Vec<u8>),
+}
+
+impl RelayerSigner {
+    /// Parses a secret which is either a hex-encoded private key (with or without `0x`)
+    /// or a mnemonic phrase.
+    pub fn parse(secret: &str) -> Result<RelayerSigner, Error> {
+        let secret = secret.trim();
+        if secret.is_empty() {
+            return Err(eyre::eyre!("Relayer secret is empty"));
+        }
+        let stripped = secret.strip_prefix("0x").unwrap_or(secret);
+        if stripped.len() == 2 * PRIVATE_KEY_BYTES && !stripped.contains(char::is_whitespace) {
+            let key = hex::decode(stripped)
+                .map_err(|e| eyre::eyre!("Invalid relayer private key: {}", e))?;
+            return Ok(RelayerSigner::PrivateKey(key));
+        }
+        Ok(RelayerSigner::Mnemonic(secret.to_owned()))
+    }
+
+    /// Reads the secret from the given environment variable at runtime.
+    pub fn from_env(var: &str) -> Result<RelayerSigner, Error> {
+        let secret = std::env::var(var)
+            .map_err(|e| eyre::eyre!("Failed to read relayer secret from ${}: {}", var, e))?;
+        RelayerSigner::parse(&secret)
+    }
+
+    /// Reads the secret from a file containing either a mnemonic phrase or a private key.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<RelayerSigner, Error> {
+        let path = path.as_ref();
+        let secret = std::fs::read_to_string(path).map_err(|e| {
+            eyre::eyre!(
+                "Failed to read relayer secret from {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+        RelayerSigner::parse(&secret)
+    }
+
+    /// Builds the wallet of this signer for the given chain.
+    pub fn wallet(&self, chain_id: u64) -> Result<LocalWallet, Error> {
+        let wallet: LocalWallet = match self {
+            RelayerSigner::Mnemonic(phrase) => MnemonicBuilder::<English>::default()
+                .phrase(phrase.as_str())
+                .build()
+                .map_err(|e| eyre::eyre!("Failed to build wallet from mnemonic: {}", e))?,
+            RelayerSigner::PrivateKey(key) => SigningKey::from_slice(key.as_slice())
+                .map_err(|e| eyre::eyre!("Invalid relayer private key: {}", e))?
+                .into(),
+        };
+        Ok(wallet.with_chain_id(chain_id))
+    }
+
+    /// Returns the address of this signer.
+    pub fn address(&self) -> Result<Address, Error> {
+        Ok(self.wallet(1)?.address())
+    }
+}
+
+impl fmt::Debug for RelayerSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the secret itself.
+        match self {
+            RelayerSigner::Mnemonic(_) => f.write_str("RelayerSigner::Mnemonic(..)"),
+            RelayerSigner::PrivateKey(_) => f.write_str("RelayerSigner::PrivateKey(..)"),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Catching the treasury light client up with the simperby chain.
+
+use crate::{prepare_light_client_update, EvmCompatibleChain, TransactionOutcome};
+use ethers::types::{Bytes, TxHash};
+use eyre::Error;
+use simperby_core::{BlockHeader, BlockHeight, FinalizationProof};
+use simperby_settlement::SettlementChain;
+
+/// The headroom added to the gas estimated for the first update of a batch.
+///
+/// Later updates can't be estimated before the earlier ones are mined, so they reuse the
+/// first estimate; headers with a larger validator set cost more to verify.
+const PIPELINED_GAS_MARGIN_PERCENT: u64 = 50;
+
+/// A step of [`EvmCompatibleChain::sync_light_client`].
+#[derive(Debug, Clone)]
+pub enum LightClientSyncProgress {
+    /// The header is already applied to the light client.
+    Skipped { height: BlockHeight },
+    /// The update was sent and awaits confirmation.
+    Submitted {
+        height: BlockHeight,
+        transaction_hash: TxHash,
+    },
+    /// The update was mined.
+    Confirmed {
+        height: BlockHeight,
+        outcome: TransactionOutcome,
+    },
+}
+
+/// The result of a successful [`EvmCompatibleChain::sync_light_client`].
+#[derive(Debug, Clone)]
+pub struct LightClientSyncReport {
+    /// The height of the light client before the sync.
+    pub initial_height: BlockHeight,
+    /// The height of the light client after the sync.
+    pub final_height: BlockHeight,
+    /// The number of headers that were already applied.
+    pub skipped: usize,
+    /// The outcomes of the updates, in height order.
+    pub outcomes: Vec<TransactionOutcome>,
+}
+
+impl EvmCompatibleChain {
+    /// Brings the treasury light client up to the last of `headers_with_proofs`.
+    ///
+    /// Headers at or below the on-chain height are skipped. The rest must follow the on-chain
+    /// header one height after another; they are all verified locally before any is sent, then
+    /// sent back to back with consecutive relayer nonces and awaited in order.
+    pub async fn sync_light_client(
+        &self,
+        mut headers_with_proofs: Vec<(BlockHeader, FinalizationProof)>,
+        mut on_progress: impl FnMut(LightClientSyncProgress) + Send,
+    ) -> Result<LightClientSyncReport, Error> {
+        let contract = self.signing_treasury()?;
+        let last_header = self.get_light_client_header().await?;
+        let initial_height = last_header.height;
+        headers_with_proofs.sort_by_key(|(header, _)| header.height);
+
+        let mut skipped = 0;
+        let mut updates: Vec<(BlockHeight, Bytes, Bytes)> = Vec::new();
+        let mut last_header = Bytes::from(
+            serde_spb::to_vec(&last_header)
+                .map_err(|_| eyre::eyre!("Failed to serialize block header"))?,
+        );
+        for (header, proof) in &headers_with_proofs {
+            if header.height <= initial_height {
+                skipped += 1;
+                on_progress(LightClientSyncProgress::Skipped {
+                    height: header.height,
+                });
+                continue;
+            }
+            let (header_bytes, proof_bytes) =
+                prepare_light_client_update(&last_header, header, proof)?;
+            last_header = header_bytes.clone();
+            updates.push((header.height, header_bytes, proof_bytes));
+        }
+        let final_height = updates
+            .last()
+            .map_or(initial_height, |(height, _, _)| *height);
+        log::info!(
+            "Syncing light client from height {} to {} ({} headers skipped)",
+            initial_height,
+            final_height,
+            skipped
+        );
+        if updates.is_empty() {
+            return Ok(LightClientSyncReport {
+                initial_height,
+                final_height,
+                skipped,
+                outcomes: Vec::new(),
+            });
+        }
+
+        let (_, first_header, first_proof) = &updates[0];
+        let gas = self
+            .estimate_gas_with_margin(
+                &contract.update_light_client(first_header.clone(), first_proof.clone()),
+                PIPELINED_GAS_MARGIN_PERCENT.max(self.chain.get_gas_margin_percent()),
+            )
+            .await?;
+
+        // The relayer nonces are allocated consecutively, so the updates are sent back to back.
+        let mut pending = Vec::new();
+        let mut send_error = None;
+        for (height, header, proof) in updates {
+            let mut call = contract.update_light_client(header, proof);
+            call.tx.set_gas(gas);
+            match self.send_relayer_call(call, Some(height)).await {
+                Ok(transaction) => {
+                    on_progress(LightClientSyncProgress::Submitted {
+                        height,
+                        transaction_hash: transaction.transaction_hash,
+                    });
+                    pending.push((height, transaction));
+                }
+                Err(err) => {
+                    // The updates already sent still have to be awaited before reporting.
+                    send_error = Some(err);
+                    break;
+                }
+            }
+        }
+
+        let mut outcomes = Vec::new();
+        let mut failure = None;
+        for (height, transaction) in pending {
+            let outcome = self.wait_for_relayer_transaction(transaction).await?;
+            if !outcome.success && failure.is_none() {
+                failure = Some(outcome.clone());
+            }
+            on_progress(LightClientSyncProgress::Confirmed {
+                height,
+                outcome: outcome.clone(),
+            });
+            outcomes.push(outcome);
+        }
+        if let Some(outcome) = failure {
+            outcome.ensure_success()?;
+        }
+        if let Some(err) = send_error {
+            return Err(err);
+        }
+        Ok(LightClientSyncReport {
+            initial_height,
+            final_height,
+            skipped,
+            outcomes,
+        })
+    }
+}
@@ -0,0 +1,767 @@
+//! An off-chain mirror of `Verify.sol`.
+//!
+//! Every function here works on the same serde_spb bytes the treasury receives and fails
+//! with the [`TreasuryRevert`] the contract would revert with, so a doomed transaction
+//! can be rejected before it is sent.
+
+use crate::error::TreasuryRevert;
+use ethers::types::{Address, RecoveryMessage, Signature, H256};
+use ethers_core::utils::keccak256;
+use eyre::Error;
+use simperby_core::{BlockHeader, FinalizationProof, FinalizationSignTarget};
+
+const SIG_LENGTH: usize = 65;
+const PK_LENGTH: usize = 65;
+const HASH_LENGTH: usize = 32;
+const UINT64_LENGTH: usize = 8;
+const ENUM_LENGTH: usize = 4;
+const VERSION_LENGTH: usize = 5;
+/// The execution hash is hex-encoded right before the 4-byte tail of a serialized transaction.
+const EXECUTION_HASH_TAIL: usize = 68;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedSignature {
+    pub signature: Vec<u8>,
+    /// The uncompressed public key without its `0x04` prefix.
+    pub signer: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockFinalizationProof {
+    pub round: u64,
+    pub signatures: Vec<TypedSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validator {
+    /// The uncompressed public key without its `0x04` prefix.
+    pub validator: Vec<u8>,
+    pub voting_power: u64,
+}
+
+/// A block header decoded the way `Verify.parseHeader` does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedBlockHeader {
+    pub author: Vec<u8>,
+    pub prev_block_finalization_proof: BlockFinalizationProof,
+    pub previous_hash: [u8; 32],
+    pub block_height: u64,
+    pub timestamp: i64,
+    pub commit_merkle_root: [u8; 32],
+    pub validators: Vec<Validator>,
+    pub version: Vec<u8>,
+}
+
+/// Reads serde_spb encoded data with the bounds checks of `BytesLib`.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, offset: 0 }
+    }
+
+    fn skip(&mut self, length: usize) {
+        self.offset += length;
+    }
+
+    fn peek(&self, start: usize, length: usize, reason: &str) -> Result<&'a [u8], TreasuryRevert> {
+        self.data
+            .get(start..start + length)
+            .ok_or_else(|| TreasuryRevert::Other(reason.to_owned()))
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], TreasuryRevert> {
+        let bytes = self.peek(self.offset, length, "slice_outOfBounds")?;
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, TreasuryRevert> {
+        let bytes = self.peek(self.offset, ENUM_LENGTH, "toUint32_outOfBounds")?;
+        self.offset += ENUM_LENGTH;
+        Ok(u32::from_le_bytes(
+            bytes.try_into().expect("length is checked"),
+        ))
+    }
+
+    fn u64(&mut self) -> Result<u64, TreasuryRevert> {
+        let bytes = self.peek(self.offset, UINT64_LENGTH, "toUint64_outOfBounds")?;
+        self.offset += UINT64_LENGTH;
+        Ok(u64::from_le_bytes(
+            bytes.try_into().expect("length is checked"),
+        ))
+    }
+
+    fn bytes32(&mut self) -> Result<[u8; 32], TreasuryRevert> {
+        let bytes = self.peek(self.offset, HASH_LENGTH, "toBytes32_outOfBounds")?;
+        self.offset += HASH_LENGTH;
+        Ok(bytes.try_into().expect("length is checked"))
+    }
+
+    /// Reads a serialized public key, dropping its 1-byte prefix.
+    fn public_key(&mut self) -> Result<Vec<u8>, TreasuryRevert> {
+        let key = self.peek(self.offset + 1, PK_LENGTH - 1, "slice_outOfBounds")?;
+        self.offset += PK_LENGTH;
+        Ok(key.to_vec())
+    }
+}
+
+/// Mirrors `Verify.parseProof`.
+pub fn parse_proof(input: &[u8]) -> Result<BlockFinalizationProof, TreasuryRevert> {
+    let mut reader = Reader::new(input);
+    let round = reader.u64()?;
+    let len = reader.u64()?;
+    let body = input.len() - 2 * UINT64_LENGTH;
+    if len as usize != body / (SIG_LENGTH + PK_LENGTH) || body % (SIG_LENGTH + PK_LENGTH) != 0 {
+        return Err(TreasuryRevert::InvalidProofLength);
+    }
+    let mut signatures = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        let signature = reader.bytes(SIG_LENGTH)?.to_vec();
+        let signer = reader.public_key()?;
+        signatures.push(TypedSignature { signature, signer });
+    }
+    Ok(BlockFinalizationProof { round, signatures })
+}
+
+/// Mirrors `Verify.parseHeader`.
+pub fn parse_header(input: &[u8]) -> Result<ParsedBlockHeader, TreasuryRevert> {
+    let mut reader = Reader::new(input);
+    let author = reader.public_key()?;
+
+    let round = reader.u64()?;
+    let len = reader.u64()?;
+    let mut prev_block_finalization_proof = BlockFinalizationProof::default();
+    // As on-chain, the round of an empty proof is dropped.
+    if len != 0 {
+        let mut signatures = Vec::new();
+        for _ in 0..len {
+            let signature = reader.bytes(SIG_LENGTH)?.to_vec();
+            let signer = reader.public_key()?;
+            signatures.push(TypedSignature { signature, signer });
+        }
+        prev_block_finalization_proof = BlockFinalizationProof { round, signatures };
+    }
+
+    let previous_hash = reader.bytes32()?;
+    let block_height = reader.u64()?;
+    let timestamp = reader.u64()? as i64;
+    let commit_merkle_root = reader.bytes32()?;
+    // Skip the repository root.
+    reader.skip(HASH_LENGTH);
+
+    let validators_len = reader.u64()?;
+    let mut validators = Vec::new();
+    for _ in 0..validators_len {
+        let validator = reader.public_key()?;
+        let voting_power = reader.u64()?;
+        validators.push(Validator {
+            validator,
+            voting_power,
+        });
+    }
+
+    // The length of the version is always 5, so it is skipped.
+    reader.skip(UINT64_LENGTH);
+    let version = reader.bytes(VERSION_LENGTH)?.to_vec();
+
+    Ok(ParsedBlockHeader {
+        author,
+        prev_block_finalization_proof,
+        previous_hash,
+        block_height,
+        timestamp,
+        commit_merkle_root,
+        validators,
+        version,
+    })
+}
+
+/// Computes the data a finalization signature signs: `keccak256(blockHash ++ round)`.
+pub fn finalization_sign_target(block_hash: &[u8; 32], round: u64) -> [u8; 32] {
+    let mut data = block_hash.to_vec();
+    data.extend_from_slice(&round.to_be_bytes());
+    keccak256(data)
+}
+
+/// Mirrors `Utils.pkToAddress`.
+pub fn public_key_to_address(public_key: &[u8]) -> Address {
+    Address::from_slice(&keccak256(public_key)[12..])
+}
+
+/// Mirrors the `ecrecover` precompile, which yields the zero address for a malformed signature.
+pub fn ecrecover(hash: &[u8; 32], signature: &[u8]) -> Result<Address, TreasuryRevert> {
+    if signature.len() != SIG_LENGTH {
+        return Err(TreasuryRevert::InvalidSignatureLength);
+    }
+    if signature[64] != 27 && signature[64] != 28 {
+        return Ok(Address::zero());
+    }
+    let recovered = Signature::try_from(signature)
+        .ok()
+        .and_then(|signature| {
+            signature
+                .recover(RecoveryMessage::Hash(H256::from(*hash)))
+                .ok()
+        })
+        .unwrap_or_else(Address::zero);
+    Ok(recovered)
+}
+
+/// Mirrors `Verify.verifyHeaderToHeader`.
+pub fn verify_header_to_header(
+    prev_header: &[u8],
+    prev_block_header: &ParsedBlockHeader,
+    block_header: &ParsedBlockHeader,
+) -> Result<(), TreasuryRevert> {
+    if prev_block_header.block_height.checked_add(1) != Some(block_header.block_height) {
+        return Err(TreasuryRevert::InvalidBlockHeight);
+    }
+    if block_header.previous_hash != keccak256(prev_header) {
+        return Err(TreasuryRevert::InvalidPreviousHash);
+    }
+    if block_header.timestamp < prev_block_header.timestamp {
+        return Err(TreasuryRevert::InvalidBlockTimestamp);
+    }
+    // As on-chain, an empty validator set accepts any author.
+    if !prev_block_header.validators.is_empty()
+        && !prev_block_header
+            .validators
+            .iter()
+            .any(|validator| validator.validator == block_header.author)
+    {
+        return Err(TreasuryRevert::InvalidBlockAuthor);
+    }
+    let sign_target = finalization_sign_target(
+        &block_header.previous_hash,
+        block_header.prev_block_finalization_proof.round,
+    );
+    verify_finalization_proof(
+        prev_block_header,
+        &sign_target,
+        &block_header.prev_block_finalization_proof,
+    )
+}
+
+/// Mirrors `Verify.verifyFinalizationProof`, returning the voted voting power on success.
+pub fn verify_finalization_proof(
+    header: &ParsedBlockHeader,
+    finalization_sign_target: &[u8; 32],
+    finalization_proof: &BlockFinalizationProof,
+) -> Result<u128, TreasuryRevert> {
+    let total_voting_power: u128 = header
+        .validators
+        .iter()
+        .map(|validator| validator.voting_power as u128)
+        .sum();
+    let mut voted_voting_power: u128 = 0;
+    for (j, signature) in finalization_proof.signatures.iter().enumerate() {
+        let recovered = ecrecover(finalization_sign_target, &signature.signature)?;
+        if public_key_to_address(&signature.signer) == recovered {
+            let validator = header
+                .validators
+                .get(j)
+                .ok_or_else(|| TreasuryRevert::Other("array index out of bounds".to_owned()))?;
+            if validator.validator != signature.signer {
+                return Err(TreasuryRevert::WithoutReason);
+            }
+            voted_voting_power += validator.voting_power as u128;
+        }
+    }
+    if voted_voting_power * 3 <= total_voting_power * 2 {
+        return Err(TreasuryRevert::NotEnoughVotingPower);
+    }
+    Ok(voted_voting_power)
+}
+
+/// Mirrors `Verify.verifyTransactionCommitment`.
+pub fn verify_transaction_commitment(
+    transaction: &[u8],
+    commit_roots: &[[u8; 32]],
+    merkle_proof: &[u8],
+    block_height: u64,
+    height_offset: u64,
+) -> Result<(), TreasuryRevert> {
+    if block_height < height_offset || block_height - height_offset >= commit_roots.len() as u64 {
+        return Err(TreasuryRevert::InvalidCommitmentHeight);
+    }
+    let root = commit_roots[(block_height - height_offset) as usize];
+    let mut calculated_root = keccak256(transaction);
+
+    let mut reader = Reader::new(merkle_proof);
+    let len_of_proof = reader.u64()?;
+    for _ in 0..len_of_proof {
+        let enum_order = reader.u32()?;
+        let pair_hash = match enum_order {
+            0 | 1 => reader.bytes32()?,
+            _ => return Err(TreasuryRevert::InvalidMerkleProofEntry),
+        };
+        let mut data = Vec::with_capacity(2 * HASH_LENGTH);
+        if enum_order == 0 {
+            // Left child
+            data.extend_from_slice(&pair_hash);
+            data.extend_from_slice(&calculated_root);
+        } else {
+            // Right child
+            data.extend_from_slice(&calculated_root);
+            data.extend_from_slice(&pair_hash);
+        }
+        calculated_root = keccak256(data);
+    }
+
+    if root != calculated_root {
+        return Err(TreasuryRevert::MerkleProofVerificationFail);
+    }
+    Ok(())
+}
+
+/// Mirrors the execution hash check at the start of `EVMTreasury.execute`.
+pub fn verify_execution_hash(transaction: &[u8], execution: &[u8]) -> Result<(), TreasuryRevert> {
+    let start = transaction
+        .len()
+        .checked_sub(EXECUTION_HASH_TAIL)
+        .ok_or_else(|| TreasuryRevert::Other("slice_outOfBounds".to_owned()))?;
+    let hash_of_execution = hex::decode(&transaction[start..start + 2 * HASH_LENGTH])
+        .map_err(|_| TreasuryRevert::Other("fail".to_owned()))?;
+    if hash_of_execution != keccak256(execution) {
+        return Err(TreasuryRevert::InvalidExecutionHash);
+    }
+    Ok(())
+}
+
+/// Mirrors `EVMTreasury.updateLightClient` against the current `lastHeader` of the treasury.
+///
+/// Returns the voting power which finalized the new header.
+pub fn verify_light_client_update(
+    last_header: &[u8],
+    header: &[u8],
+    proof: &[u8],
+) -> Result<u128, TreasuryRevert> {
+    let prev_block_header = parse_header(last_header)?;
+    let block_header = parse_header(header)?;
+    let proof = parse_proof(proof)?;
+    let sign_target = finalization_sign_target(&keccak256(header), proof.round);
+
+    verify_header_to_header(last_header, &prev_block_header, &block_header)?;
+    verify_finalization_proof(&block_header, &sign_target, &proof)
+}
+
+/// A finalization proof rebuilt to match the validator order the treasury checks it against.
+#[derive(Debug, Clone)]
+pub struct NormalizedFinalizationProof {
+    pub proof: FinalizationProof,
+    /// The voting power of the validators whose valid signature is in the proof.
+    pub voted_voting_power: u128,
+    pub total_voting_power: u128,
+}
+
+impl NormalizedFinalizationProof {
+    /// Whether the proof reaches the 2/3 voting power the treasury requires.
+    pub fn is_sufficient(&self) -> bool {
+        self.voted_voting_power * 3 > self.total_voting_power * 2
+    }
+}
+
+/// Rebuilds `proof` so that its `j`-th signature belongs to the `j`-th validator of `header`,
+/// as `verifyFinalizationProof` compares them by index.
+///
+/// Signatures from non-validators and invalid signatures are dropped, and absent validators
+/// get a zero signature which the treasury skips.
+pub fn normalize_finalization_proof(
+    header: &BlockHeader,
+    proof: &FinalizationProof,
+) -> Result<NormalizedFinalizationProof, Error> {
+    let header_bytes =
+        serde_spb::to_vec(header).map_err(|_| eyre::eyre!("Failed to serialize block header"))?;
+    let sign_target = finalization_sign_target(&keccak256(header_bytes), proof.round);
+    let is_valid = |signature: &simperby_core::TypedSignature<FinalizationSignTarget>| {
+        serde_spb::to_vec(signature).map_or(false, |bytes| {
+            bytes.len() == SIG_LENGTH + PK_LENGTH
+                && ecrecover(&sign_target, &bytes[..SIG_LENGTH])
+                    == Ok(public_key_to_address(&bytes[SIG_LENGTH + 1..]))
+        })
+    };
+    let mut aligned = header
+        .validator_set
+        .iter()
+        .map(|(validator, _)| {
+            proof
+                .signatures
+                .iter()
+                .find(|signature| signature.signer() == validator && is_valid(*signature))
+                .cloned()
+        })
+        .collect::<Vec<_>>();
+    // Trailing absent validators need no placeholder.
+    while let Some(None) = aligned.last() {
+        aligned.pop();
+    }
+    let mut voted_voting_power = 0;
+    let signatures = aligned
+        .into_iter()
+        .zip(header.validator_set.iter())
+        .map(|(signature, (validator, voting_power))| match signature {
+            Some(signature) => {
+                voted_voting_power += *voting_power as u128;
+                signature
+            }
+            None => simperby_core::TypedSignature::new(
+                simperby_core::Signature::zero(),
+                validator.clone(),
+            ),
+        })
+        .collect();
+    Ok(NormalizedFinalizationProof {
+        proof: FinalizationProof {
+            round: proof.round,
+            signatures,
+        },
+        voted_voting_power,
+        total_voting_power: header
+            .validator_set
+            .iter()
+            .map(|(_, voting_power)| *voting_power as u128)
+            .sum(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simperby_core::{test_utils, Hash256, PrivateKey};
+
+    /// The genesis header of a standard test chain, with its proof and validator keys.
+    fn genesis() -> (BlockHeader, FinalizationProof, Vec<PrivateKey>) {
+        let (reserved_state, validators) = test_utils::generate_standard_genesis(4);
+        (
+            reserved_state.genesis_info.header.clone(),
+            reserved_state.genesis_info.genesis_proof,
+            validators
+                .into_iter()
+                .map(|(_, private_key)| private_key)
+                .collect(),
+        )
+    }
+
+    fn next_header(header: &BlockHeader, proof: &FinalizationProof) -> BlockHeader {
+        BlockHeader {
+            author: header.validator_set[0].0.clone(),
+            prev_block_finalization_proof: proof.clone(),
+            previous_hash: header.to_hash256(),
+            height: header.height + 1,
+            timestamp: header.timestamp,
+            commit_merkle_root: Hash256::zero(),
+            repository_merkle_root: Hash256::zero(),
+            validator_set: header.validator_set.clone(),
+            version: header.version.clone(),
+        }
+    }
+
+    /// Signs `header` with `keys`, in the order of its validator set.
+    fn finalize(header: &BlockHeader, keys: &[PrivateKey]) -> FinalizationProof {
+        let target = FinalizationSignTarget {
+            round: 0,
+            block_hash: header.to_hash256(),
+        };
+        let signatures = header
+            .validator_set
+            .iter()
+            .filter_map(|(validator, _)| {
+                keys.iter()
+                    .find(|key| &key.public_key() == validator)
+                    .map(|key| simperby_core::TypedSignature::sign(&target, key).unwrap())
+            })
+            .collect();
+        FinalizationProof {
+            round: 0,
+            signatures,
+        }
+    }
+
+    fn total_voting_power(header: &BlockHeader) -> u128 {
+        header
+            .validator_set
+            .iter()
+            .map(|(_, voting_power)| *voting_power as u128)
+            .sum()
+    }
+
+    #[test]
+    fn light_client_update() {
+        let (genesis, genesis_proof, keys) = genesis();
+        let last_header = serde_spb::to_vec(&genesis).unwrap();
+        let update = |header: &BlockHeader, keys: &[PrivateKey]| {
+            let proof = finalize(header, keys);
+            verify_light_client_update(
+                &last_header,
+                &serde_spb::to_vec(header).unwrap(),
+                &serde_spb::to_vec(&proof).unwrap(),
+            )
+        };
+
+        let header = next_header(&genesis, &genesis_proof);
+        assert_eq!(update(&header, &keys), Ok(total_voting_power(&genesis)));
+
+        let mut wrong_height = header.clone();
+        wrong_height.height += 1;
+        assert_eq!(
+            update(&wrong_height, &keys),
+            Err(TreasuryRevert::InvalidBlockHeight)
+        );
+        let mut wrong_previous_hash = header.clone();
+        wrong_previous_hash.previous_hash = Hash256::zero();
+        assert_eq!(
+            update(&wrong_previous_hash, &keys),
+            Err(TreasuryRevert::InvalidPreviousHash)
+        );
+        assert_eq!(
+            update(&header, &keys[..2]),
+            Err(TreasuryRevert::NotEnoughVotingPower)
+        );
+    }
+
+    #[test]
+    fn parse_genesis_header() {
+        let (genesis, _, _) = genesis();
+        let bytes = serde_spb::to_vec(&genesis).unwrap();
+        let parsed = parse_header(&bytes).unwrap();
+        assert_eq!(
+            parsed.author,
+            serde_spb::to_vec(&genesis.author).unwrap()[1..]
+        );
+        assert_eq!(parsed.block_height, genesis.height);
+        assert_eq!(parsed.timestamp, genesis.timestamp);
+        assert_eq!(
+            parsed.previous_hash,
+            serde_spb::to_vec(&genesis.previous_hash).unwrap()[..]
+        );
+        assert_eq!(
+            parsed.prev_block_finalization_proof.signatures.len(),
+            genesis.prev_block_finalization_proof.signatures.len()
+        );
+        assert_eq!(parsed.validators.len(), genesis.validator_set.len());
+        for (validator, (public_key, voting_power)) in
+            parsed.validators.iter().zip(&genesis.validator_set)
+        {
+            assert_eq!(
+                validator.validator,
+                serde_spb::to_vec(public_key).unwrap()[1..]
+            );
+            assert_eq!(validator.voting_power, *voting_power);
+        }
+        assert!(parse_header(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn header_to_header() {
+        let (genesis, genesis_proof, _) = genesis();
+        let prev_header = serde_spb::to_vec(&genesis).unwrap();
+        let parsed_genesis = parse_header(&prev_header).unwrap();
+        let parse = |header: &BlockHeader| parse_header(&serde_spb::to_vec(header).unwrap());
+
+        let header = next_header(&genesis, &genesis_proof);
+        verify_header_to_header(&prev_header, &parsed_genesis, &parse(&header).unwrap()).unwrap();
+
+        let mut earlier = header.clone();
+        earlier.timestamp = genesis.timestamp - 1;
+        assert_eq!(
+            verify_header_to_header(&prev_header, &parsed_genesis, &parse(&earlier).unwrap()),
+            Err(TreasuryRevert::InvalidBlockTimestamp)
+        );
+        let mut unproven = header;
+        unproven
+            .prev_block_finalization_proof
+            .signatures
+            .truncate(1);
+        assert_eq!(
+            verify_header_to_header(&prev_header, &parsed_genesis, &parse(&unproven).unwrap()),
+            Err(TreasuryRevert::NotEnoughVotingPower)
+        );
+    }
+
+    #[test]
+    fn recover_signer() {
+        let (genesis, genesis_proof, keys) = genesis();
+        let header = next_header(&genesis, &genesis_proof);
+        let target = finalization_sign_target(&keccak256(serde_spb::to_vec(&header).unwrap()), 0);
+        let signature = serde_spb::to_vec(&finalize(&header, &keys).signatures[0]).unwrap();
+        let (signature, signer) = signature.split_at(SIG_LENGTH);
+        assert_eq!(
+            ecrecover(&target, signature),
+            Ok(public_key_to_address(&signer[1..]))
+        );
+        assert_eq!(
+            ecrecover(&target, &signature[1..]),
+            Err(TreasuryRevert::InvalidSignatureLength)
+        );
+        let mut malformed = signature.to_vec();
+        malformed[64] = 0;
+        assert_eq!(ecrecover(&target, &malformed), Ok(Address::zero()));
+    }
+
+    #[test]
+    fn finalization_proof() {
+        let (genesis, genesis_proof, keys) = genesis();
+        let header = next_header(&genesis, &genesis_proof);
+        let header_bytes = serde_spb::to_vec(&header).unwrap();
+        let parsed_header = parse_header(&header_bytes).unwrap();
+        let target = finalization_sign_target(&keccak256(&header_bytes), 0);
+        let proof = |proof: &FinalizationProof| parse_proof(&serde_spb::to_vec(proof).unwrap());
+
+        let mut finalization_proof = finalize(&header, &keys);
+        assert_eq!(
+            verify_finalization_proof(
+                &parsed_header,
+                &target,
+                &proof(&finalization_proof).unwrap()
+            ),
+            Ok(total_voting_power(&header))
+        );
+        // Signatures are matched with the validators by index.
+        finalization_proof.signatures.swap(0, 1);
+        assert_eq!(
+            verify_finalization_proof(
+                &parsed_header,
+                &target,
+                &proof(&finalization_proof).unwrap()
+            ),
+            Err(TreasuryRevert::WithoutReason)
+        );
+        // A signature of another block counts for nothing.
+        let other_target = finalization_sign_target(&[0u8; 32], 0);
+        assert_eq!(
+            verify_finalization_proof(
+                &parsed_header,
+                &other_target,
+                &proof(&finalize(&header, &keys)).unwrap()
+            ),
+            Err(TreasuryRevert::NotEnoughVotingPower)
+        );
+    }
+
+    fn merkle_proof(entries: &[(u32, [u8; 32])]) -> Vec<u8> {
+        let mut proof = (entries.len() as u64).to_le_bytes().to_vec();
+        for (order, hash) in entries {
+            proof.extend_from_slice(&order.to_le_bytes());
+            proof.extend_from_slice(hash);
+        }
+        proof
+    }
+
+    #[test]
+    fn normalize_proof() {
+        let (genesis, genesis_proof, keys) = genesis();
+        let header = next_header(&genesis, &genesis_proof);
+        let (_, outsider) = simperby_core::generate_keypair_random();
+        // Every validator but the second, in reverse order, and a non-validator.
+        let mut signers = vec![outsider];
+        signers.extend(
+            keys.iter()
+                .filter(|key| key.public_key() != header.validator_set[1].0)
+                .cloned(),
+        );
+        signers.reverse();
+        let target = FinalizationSignTarget {
+            round: 0,
+            block_hash: header.to_hash256(),
+        };
+        let proof = FinalizationProof {
+            round: 0,
+            signatures: signers
+                .iter()
+                .map(|key| simperby_core::TypedSignature::sign(&target, key).unwrap())
+                .collect(),
+        };
+
+        let normalized = normalize_finalization_proof(&header, &proof).unwrap();
+        let signatures = &normalized.proof.signatures;
+        assert_eq!(signatures.len(), header.validator_set.len());
+        for (signature, (validator, _)) in signatures.iter().zip(&header.validator_set) {
+            assert_eq!(signature.signer(), validator);
+        }
+        assert_eq!(
+            signatures[1],
+            simperby_core::TypedSignature::new(
+                simperby_core::Signature::zero(),
+                header.validator_set[1].0.clone()
+            )
+        );
+        let voting_power = |index: usize| header.validator_set[index].1 as u128;
+        assert_eq!(
+            normalized.voted_voting_power,
+            voting_power(0) + voting_power(2) + voting_power(3)
+        );
+        assert_eq!(normalized.total_voting_power, total_voting_power(&header));
+        assert!(normalized.is_sufficient());
+
+        let header_bytes = serde_spb::to_vec(&header).unwrap();
+        assert_eq!(
+            verify_finalization_proof(
+                &parse_header(&header_bytes).unwrap(),
+                &finalization_sign_target(&keccak256(&header_bytes), 0),
+                &parse_proof(&serde_spb::to_vec(&normalized.proof).unwrap()).unwrap()
+            ),
+            Ok(normalized.voted_voting_power)
+        );
+
+        // Trailing absent validators get no placeholder.
+        let last = &header.validator_set[header.validator_set.len() - 1].0;
+        let proof = FinalizationProof {
+            round: 0,
+            signatures: proof
+                .signatures
+                .into_iter()
+                .filter(|signature| signature.signer() != last)
+                .collect(),
+        };
+        let normalized = normalize_finalization_proof(&header, &proof).unwrap();
+        assert_eq!(
+            normalized.proof.signatures.len(),
+            header.validator_set.len() - 1
+        );
+        assert!(!normalized.is_sufficient());
+    }
+
+    #[test]
+    fn transaction_commitment() {
+        let transaction = b"transaction".to_vec();
+        let left = [1u8; 32];
+        let right = [2u8; 32];
+        let leaf = keccak256(&transaction);
+        let node = keccak256([left, leaf].concat());
+        let root = keccak256([node, right].concat());
+        let proof = merkle_proof(&[(0, left), (1, right)]);
+
+        verify_transaction_commitment(&transaction, &[[0u8; 32], root], &proof, 11, 10).unwrap();
+        assert_eq!(
+            verify_transaction_commitment(&transaction, &[root], &proof, 11, 10),
+            Err(TreasuryRevert::InvalidCommitmentHeight)
+        );
+        assert_eq!(
+            verify_transaction_commitment(&transaction, &[root, root], &proof, 10, 10),
+            Ok(())
+        );
+        let swapped = merkle_proof(&[(1, left), (0, right)]);
+        assert_eq!(
+            verify_transaction_commitment(&transaction, &[root], &swapped, 10, 10),
+            Err(TreasuryRevert::MerkleProofVerificationFail)
+        );
+        let invalid = merkle_proof(&[(2, left)]);
+        assert_eq!(
+            verify_transaction_commitment(&transaction, &[root], &invalid, 10, 10),
+            Err(TreasuryRevert::InvalidMerkleProofEntry)
+        );
+    }
+
+    #[test]
+    fn proof_length() {
+        let mut proof = 0u64.to_le_bytes().to_vec();
+        proof.extend_from_slice(&1u64.to_le_bytes());
+        assert_eq!(parse_proof(&proof), Err(TreasuryRevert::InvalidProofLength));
+        proof.extend_from_slice(&[0u8; SIG_LENGTH + PK_LENGTH]);
+        assert_eq!(parse_proof(&proof).unwrap().signatures.len(), 1);
+    }
+}
@@ -0,0 +1,31 @@
+import { ethers } from "hardhat";
+
+// The addresses of the first deployments on a fresh local node, which the client tests use.
+const erc721MockAddress = "0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512";
+const treasuryAddress = "0x9fE46736679d2D9a65F0992F2272dE9f3c7fa6e0";
+
+// The client test `get_treasury_non_fungible_token_balance` expects exactly these IDs.
+const tokenIds = [1, 2, 3];
+
+async function main() {
+  const erc721mock = await ethers.getContractAt(
+    "ERC721Mock",
+    erc721MockAddress
+  );
+
+  for (const tokenId of tokenIds) {
+    const tx = await erc721mock.mint(treasuryAddress, tokenId);
+    await tx.wait();
+  }
+
+  console.log(
+    `Minted ERC721Mock tokens ${tokenIds.join(", ")} to ${treasuryAddress}`
+  );
+}
+
+// We recommend this pattern to be able to use async/await everywhere
+// and properly handle errors.
+main().catch((error) => {
+  console.error(error);
+  process.exitCode = 1;
+});